@@ -0,0 +1,558 @@
+//! BSI AIS 20/31 test procedures A and B (AIS 31, version 1, section 5.5).
+
+use nistrs::{BitsData, TestResultT};
+
+use crate::fips;
+use crate::tests::{NistWrapper, ParamSpec, RandomnessTest, TestParam};
+
+use std::sync::Arc;
+
+/// Number of 48-bit words tested by T0.
+const T0_WORDS: usize = 1 << 16;
+const T0_BITS: usize = T0_WORDS * 48;
+
+/// Number of sequences of [`fips::BLOCK_BITS`] tested by T1 - T5.
+const A_SEQUENCES: usize = 257;
+
+/// Length of a block tested by procedure A.
+pub const PROCEDURE_A_BITS: usize = T0_BITS + A_SEQUENCES * fips::BLOCK_BITS;
+
+/// Number of bits of T6a and of tuples per prefix of T6b, T7a and T7b.
+const B_SAMPLES: usize = 100_000;
+
+/// Parameters of the entropy test T8.
+const T8_L: usize = 8;
+const T8_Q: usize = 2560;
+const T8_K: usize = 256_000;
+
+/// Bits used by procedure B when all prefixes of tuples are equally likely.
+pub const PROCEDURE_B_BITS: usize = B_SAMPLES * (1 + 2 * 2 + 3 * 4 + 4 * 8) + (T8_Q + T8_K) * T8_L;
+
+/// Allowed numbers of runs of length 1, 2, 3, 4, 5 and 6+ of each bit (T3).
+const RUNS_INTERVALS: [(usize, usize); 6] = [
+    (2267, 2733),
+    (1079, 1421),
+    (502, 748),
+    (223, 402),
+    (90, 223),
+    (90, 223),
+];
+
+/// Chi-square bound of the homogeneity tests T7a and T7b (1 degree of freedom, alpha = 0.0001).
+const T7_BOUND: f64 = 15.13;
+
+fn to_usize(bits: &[bool]) -> usize {
+    bits.iter().fold(0, |acc, bit| (acc << 1) | *bit as usize)
+}
+
+/// T0: 2^16 48-bit words must be pairwise different, returns the number of repeated words.
+fn disjointness_test(bits: &[bool]) -> TestResultT {
+    let mut words: Vec<usize> = bits[..T0_BITS].chunks(48).map(to_usize).collect();
+    words.sort_unstable();
+    let repeated = words.windows(2).filter(|w| w[0] == w[1]).count();
+    (repeated == 0, repeated as f64)
+}
+
+fn monobit_test(bits: &[bool]) -> bool {
+    let ones = bits.iter().filter(|bit| **bit).count();
+    9654 < ones && ones < 10346
+}
+
+fn poker_test(bits: &[bool]) -> bool {
+    let mut f = [0_usize; 16];
+    bits.chunks(4).for_each(|v| f[to_usize(v)] += 1);
+    let x = 16_f64 / 5000_f64 * f.iter().map(|v| (v * v) as f64).sum::<f64>() - 5000_f64;
+    1.03 < x && x < 57.4
+}
+
+fn runs_test(bits: &[bool]) -> bool {
+    let mut counts = [[0_usize; 6]; 2];
+    fips::runs(bits)
+        .into_iter()
+        .for_each(|(bit, len)| counts[bit as usize][len.min(6) - 1] += 1);
+
+    counts
+        .iter()
+        .flat_map(|c| c.iter().zip(RUNS_INTERVALS))
+        .all(|(count, (min, max))| (min..=max).contains(count))
+}
+
+fn long_run_test(bits: &[bool]) -> bool {
+    fips::runs(bits).into_iter().all(|(_, len)| len < 34)
+}
+
+/// Packs bits into 64-bit words, the first bit is the highest bit of the first word.
+fn pack(bits: &[bool]) -> Vec<u64> {
+    bits.chunks(64)
+        .map(|v| {
+            let word = v.iter().fold(0_u64, |acc, bit| (acc << 1) | *bit as u64);
+            word << (64 - v.len())
+        })
+        .collect()
+}
+
+/// 64 bits starting at the bit `pos`.
+fn word_at(words: &[u64], pos: usize) -> u64 {
+    let (i, shift) = (pos / 64, pos % 64);
+    let next = words.get(i + 1).copied().unwrap_or(0);
+    if shift == 0 {
+        words[i]
+    } else {
+        (words[i] << shift) | (next >> (64 - shift))
+    }
+}
+
+/// Number of `j` in `start..start + 5000` with `b[j] != b[j + tau]`.
+fn autocorrelation(words: &[u64], start: usize, tau: usize) -> usize {
+    const N: usize = 5000;
+
+    (0..N)
+        .step_by(64)
+        .map(|j| {
+            let len = (N - j).min(64);
+            let diff = word_at(words, start + j) ^ word_at(words, start + j + tau);
+            (diff >> (64 - len)).count_ones() as usize
+        })
+        .sum()
+}
+
+/// T5: the shift with the largest deviation in the first half is tested on the second half.
+fn autocorrelation_test(bits: &[bool]) -> bool {
+    let words = pack(bits);
+
+    let tau = (1..=5000)
+        .max_by_key(|tau| autocorrelation(&words, 0, *tau).abs_diff(2500))
+        .unwrap_or(1);
+
+    let z = autocorrelation(&words, 10_000, tau);
+    2326 < z && z < 2674
+}
+
+/// Procedure A: T0 once, then T1 - T5 on 257 sequences of 20000 bits.
+/// T1 - T5 return the number of failed sequences.
+fn procedure_a(bits: &[bool]) -> Vec<TestResultT> {
+    let tests: [fn(&[bool]) -> bool; 5] = [
+        monobit_test,
+        poker_test,
+        runs_test,
+        long_run_test,
+        autocorrelation_test,
+    ];
+
+    let mut failed = [0_usize; 5];
+    bits[T0_BITS..PROCEDURE_A_BITS]
+        .chunks(fips::BLOCK_BITS)
+        .for_each(|seq| {
+            tests
+                .iter()
+                .zip(&mut failed)
+                .filter(|(test, _)| !test(seq))
+                .for_each(|(_, failed)| *failed += 1);
+        });
+
+    std::iter::once(disjointness_test(bits))
+        .chain(failed.iter().map(|v| (*v == 0, *v as f64)))
+        .collect()
+}
+
+/// Bits of the sequence consumed one after another by the tests of procedure B.
+struct Reader<'a> {
+    bits: &'a [bool],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [bool], String> {
+        let bits = self
+            .bits
+            .get(self.pos..self.pos + n)
+            .ok_or(format!("sequence ends after {} bits", self.bits.len()))?;
+        self.pos += n;
+        Ok(bits)
+    }
+
+    /// Counts ones at the last place of disjoint `k`-tuples until
+    /// [`B_SAMPLES`] tuples with each prefix are read. Returns the numbers of ones by prefix.
+    fn tuples(&mut self, k: usize) -> Result<Vec<usize>, String> {
+        let prefixes = 1 << (k - 1);
+        let mut total = vec![0_usize; prefixes];
+        let mut ones = vec![0_usize; prefixes];
+
+        while total.iter().any(|v| *v < B_SAMPLES) {
+            let tuple = self.take(k)?;
+            let prefix = to_usize(&tuple[..k - 1]);
+            if total[prefix] < B_SAMPLES {
+                total[prefix] += 1;
+                ones[prefix] += tuple[k - 1] as usize;
+            }
+        }
+
+        Ok(ones)
+    }
+}
+
+/// Chi-square statistic of the homogeneity of two samples of [`B_SAMPLES`] bits with `a` and `b` ones.
+fn homogeneity(a: usize, b: usize) -> f64 {
+    let n = B_SAMPLES as f64;
+    let ones = (a + b) as f64 / 2_f64;
+    let zeros = n - ones;
+
+    [a as f64, b as f64]
+        .iter()
+        .map(|v| (v - ones).powi(2) / ones + (n - v - zeros).powi(2) / zeros)
+        .sum()
+}
+
+/// T7: the next bit must not depend on the oldest bit of the prefix,
+/// returns the largest statistic of the prefixes.
+fn homogeneity_test(ones: &[usize]) -> TestResultT {
+    let half = ones.len() / 2;
+    let stat = (0..half)
+        .map(|i| homogeneity(ones[i], ones[i + half]))
+        .fold(0_f64, f64::max);
+    (stat < T7_BOUND, stat)
+}
+
+/// T8: Coron's entropy test with L = 8, Q = 2560 and K = 256000.
+fn entropy_test(bits: &[bool]) -> TestResultT {
+    let words: Vec<usize> = bits.chunks(T8_L).map(to_usize).collect();
+
+    // g(i) = (1 / ln 2) * sum(1 / k, k = 1..i - 1)
+    let mut g = vec![0_f64; T8_Q + T8_K + 1];
+    (2..g.len()).for_each(|i| g[i] = g[i - 1] + 1_f64 / (i - 1) as f64);
+
+    let mut last = vec![0_usize; 1 << T8_L];
+    (1..=T8_Q).for_each(|i| last[words[i - 1]] = i);
+
+    let sum: f64 = (T8_Q + 1..=T8_Q + T8_K)
+        .map(|i| {
+            let word = words[i - 1];
+            let dist = i - last[word];
+            last[word] = i;
+            g[dist]
+        })
+        .sum();
+
+    let f = sum / T8_K as f64 / std::f64::consts::LN_2;
+    (f > 7.976, f)
+}
+
+/// Procedure B: T6a, T6b, T7a, T7b and T8 on consecutive parts of the sequence.
+fn procedure_b(bits: &[bool]) -> Result<Vec<TestResultT>, String> {
+    let mut reader = Reader { bits, pos: 0 };
+
+    let ones = reader.take(B_SAMPLES)?.iter().filter(|bit| **bit).count();
+    let t6a = (ones as f64 / B_SAMPLES as f64 - 0.5).abs();
+
+    let pairs = reader.tuples(2)?;
+    let t6b = (pairs[0] as f64 - pairs[1] as f64).abs() / B_SAMPLES as f64;
+
+    let t7a = homogeneity_test(&reader.tuples(3)?);
+    let t7b = homogeneity_test(&reader.tuples(4)?);
+    let t8 = entropy_test(reader.take((T8_Q + T8_K) * T8_L)?);
+
+    Ok(vec![(t6a < 0.025, t6a), (t6b < 0.02, t6b), t7a, t7b, t8])
+}
+
+struct ProcedureA;
+
+impl RandomnessTest for ProcedureA {
+    fn name(&self) -> &'static str {
+        "ProcedureA"
+    }
+
+    fn min_length(&self, _params: &[TestParam]) -> usize {
+        PROCEDURE_A_BITS
+    }
+
+    fn run(
+        &self,
+        data: &BitsData,
+        _params: &[TestParam],
+    ) -> Result<Vec<TestResultT>, Option<String>> {
+        if data.len() < PROCEDURE_A_BITS {
+            return Err(Some(format!(
+                "needs {} bits, got {}",
+                PROCEDURE_A_BITS,
+                data.len()
+            )));
+        }
+
+        Ok(procedure_a(&data[..]))
+    }
+
+    fn labels(&self, _params: &[TestParam]) -> Vec<String> {
+        ["T0", "T1", "T2", "T3", "T4", "T5"]
+            .map(String::from)
+            .to_vec()
+    }
+}
+
+struct ProcedureB {
+    params: [ParamSpec; 1],
+}
+
+impl RandomnessTest for ProcedureB {
+    fn name(&self) -> &'static str {
+        "ProcedureB"
+    }
+
+    fn params(&self) -> &[ParamSpec] {
+        &self.params
+    }
+
+    fn min_length(&self, params: &[TestParam]) -> usize {
+        PROCEDURE_B_BITS * 8 / params[0].int()
+    }
+
+    fn run(
+        &self,
+        data: &BitsData,
+        params: &[TestParam],
+    ) -> Result<Vec<TestResultT>, Option<String>> {
+        // Raw samples are stored one per byte, the digitized bits are the lowest bits of it.
+        let width = params[0].int();
+        let bits: Vec<bool> = if width < 8 {
+            data[..]
+                .chunks(8)
+                .flat_map(|sample| &sample[sample.len().saturating_sub(width)..])
+                .copied()
+                .collect()
+        } else {
+            data[..].to_vec()
+        };
+
+        procedure_b(&bits).map_err(Some)
+    }
+
+    fn labels(&self, _params: &[TestParam]) -> Vec<String> {
+        ["T6a", "T6b", "T7a", "T7b", "T8"]
+            .map(String::from)
+            .to_vec()
+    }
+}
+
+/// The AIS 31 procedures A and B.
+pub fn ais31_tests() -> Vec<NistWrapper> {
+    vec![
+        NistWrapper::new(Arc::new(ProcedureA)),
+        NistWrapper::new(Arc::new(ProcedureB {
+            params: [ParamSpec::int(
+                "Sample bits",
+                "Bits of a raw sample stored in each byte, 8 - the input is a plain bit stream",
+                1,
+                8,
+                8,
+            )],
+        })),
+    ]
+}
+
+/// Number of failed single tests of a procedure on a block: T1 - T5 of procedure A
+/// report the number of failed sequences, the rest of the tests count once.
+pub fn failures(label: &str, value: f64) -> usize {
+    match label {
+        "T1" | "T2" | "T3" | "T4" | "T5" => value as usize,
+        _ => 1,
+    }
+}
+
+/// Outcome of applying a procedure to consecutive blocks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Verdict {
+    pub passed: usize,
+    /// Passed after a repetition caused by a single failed test.
+    pub repeated: usize,
+    pub failed: usize,
+}
+
+/// Applies the AIS 31 decision rule to `failures` by block: a procedure passes without failed tests,
+/// with exactly one failed test it is repeated once on the next block and must pass without failures.
+pub fn verdict(failures: &[usize]) -> Verdict {
+    let mut verdict = Verdict::default();
+    let mut blocks = failures.iter();
+
+    while let Some(failed) = blocks.next() {
+        match failed {
+            0 => verdict.passed += 1,
+            1 => match blocks.next() {
+                Some(0) => verdict.repeated += 1,
+                _ => verdict.failed += 1,
+            },
+            _ => verdict.failed += 1,
+        }
+    }
+
+    verdict
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `n` bits of SplitMix64 seeded with `seed`.
+    fn random_bits(n: usize, seed: u64) -> Vec<bool> {
+        let mut x = seed;
+        let mut bits = Vec::with_capacity(n + 63);
+        while bits.len() < n {
+            x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = x;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+            bits.extend((0..64).rev().map(|i| z >> i & 1 == 1));
+        }
+        bits.truncate(n);
+        bits
+    }
+
+    #[test]
+    fn disjointness() {
+        // Words 0, 1, 2, ... are all different.
+        let counter: Vec<bool> = (0..T0_WORDS as u64)
+            .flat_map(|word| (0..48).rev().map(move |i| word >> i & 1 == 1))
+            .collect();
+        assert_eq!(disjointness_test(&counter), (true, 0_f64));
+
+        let mut repeated = counter;
+        repeated[48..96].fill(false);
+        assert_eq!(disjointness_test(&repeated), (false, 1_f64));
+    }
+
+    #[test]
+    fn random_sequences_pass() {
+        for seed in 0..3 {
+            let bits = random_bits(fips::BLOCK_BITS, seed);
+            assert!(monobit_test(&bits), "T1, seed {}", seed);
+            assert!(poker_test(&bits), "T2, seed {}", seed);
+            assert!(runs_test(&bits), "T3, seed {}", seed);
+            assert!(long_run_test(&bits), "T4, seed {}", seed);
+            assert!(autocorrelation_test(&bits), "T5, seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn biased_sequences_fail() {
+        let alternating: Vec<bool> = (0..fips::BLOCK_BITS).map(|i| i % 2 == 0).collect();
+        assert!(monobit_test(&alternating));
+        assert!(!poker_test(&alternating));
+        assert!(!runs_test(&alternating));
+        assert!(!autocorrelation_test(&alternating));
+
+        let mut ones = random_bits(fips::BLOCK_BITS, 0);
+        ones[..fips::BLOCK_BITS / 10].fill(true);
+        assert!(!monobit_test(&ones));
+    }
+
+    #[test]
+    fn long_run() {
+        let mut bits = random_bits(fips::BLOCK_BITS, 1);
+        bits[1000] = false;
+        bits[1001..1034].fill(true);
+        bits[1034] = false;
+        assert!(long_run_test(&bits));
+        bits[1034] = true;
+        bits[1035] = false;
+        assert!(!long_run_test(&bits));
+    }
+
+    #[test]
+    fn repeated_sequence_fails_autocorrelation() {
+        // The second half repeats the first one shifted by 100 bits.
+        let mut bits = random_bits(fips::BLOCK_BITS, 2);
+        (100..fips::BLOCK_BITS).for_each(|i| bits[i] = bits[i - 100]);
+        assert!(!autocorrelation_test(&bits));
+    }
+
+    #[test]
+    fn homogeneity() {
+        let half = B_SAMPLES / 2;
+        assert_eq!(homogeneity_test(&[half, half]), (true, 0_f64));
+        assert!(homogeneity_test(&[half, half, half + 500, half]).0);
+        assert!(!homogeneity_test(&[half, half, half + 1000, half]).0);
+    }
+
+    #[test]
+    fn procedure_b() {
+        let bits = random_bits(PROCEDURE_B_BITS + 100_000, 3);
+        let results = super::procedure_b(&bits).unwrap();
+        assert_eq!(results.len(), 5);
+        assert!(results.iter().all(|(passed, _)| *passed), "{:?}", results);
+
+        // The second bit of a pair repeats the first one with the probability 3/4.
+        let flips = random_bits(2 * bits.len(), 5);
+        let pairs: Vec<bool> = bits
+            .iter()
+            .zip(flips.chunks(2))
+            .flat_map(|(bit, flip)| [*bit, *bit ^ (flip[0] & flip[1])])
+            .collect();
+        let results = super::procedure_b(&pairs).unwrap();
+        assert!(results[0].0, "T6a {:?}", results[0]);
+        assert!(!results[1].0, "T6b {:?}", results[1]);
+        assert!((results[1].1 - 0.5).abs() < 0.01, "T6b {:?}", results[1]);
+        assert!(!results[4].0, "T8 {:?}", results[4]);
+
+        assert!(super::procedure_b(&bits[..B_SAMPLES]).is_err());
+    }
+
+    #[test]
+    fn entropy() {
+        let (passed, f) = entropy_test(&random_bits((T8_Q + T8_K) * T8_L, 4));
+        assert!(passed, "{}", f);
+        assert!(f < 8.1, "{}", f);
+    }
+
+    #[test]
+    fn counts_failures() {
+        assert_eq!(failures("T0", 3_f64), 1);
+        assert_eq!(failures("T1", 3_f64), 3);
+        assert_eq!(failures("T8", 7.9), 1);
+    }
+
+    #[test]
+    fn repeats_once() {
+        assert_eq!(verdict(&[]), Verdict::default());
+        assert_eq!(
+            verdict(&[0, 0]),
+            Verdict {
+                passed: 2,
+                repeated: 0,
+                failed: 0
+            }
+        );
+        // A single failure is repeated on the next block, which must pass.
+        assert_eq!(
+            verdict(&[1, 0, 0]),
+            Verdict {
+                passed: 1,
+                repeated: 1,
+                failed: 0
+            }
+        );
+        assert_eq!(
+            verdict(&[1, 1, 0]),
+            Verdict {
+                passed: 1,
+                repeated: 0,
+                failed: 1
+            }
+        );
+        // Two failures fail at once, a failure on the last block can't be repeated.
+        assert_eq!(
+            verdict(&[2, 0, 1]),
+            Verdict {
+                passed: 1,
+                repeated: 0,
+                failed: 2
+            }
+        );
+        assert_eq!(
+            verdict(&[usize::MAX, 1, 0]),
+            Verdict {
+                passed: 0,
+                repeated: 1,
+                failed: 1
+            }
+        );
+    }
+}
@@ -0,0 +1,442 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+
+use crate::error::TestError;
+use crate::health::{HealthConfig, HealthTests};
+use crate::input::{self, BitOrder, BlockReader, Decoder, InputFormat, Region, Tap, Unpacker};
+use crate::report::{
+    add_health, calc_ais_stat, calc_entropy_stat, calc_gmt_stat, calc_pass_stat, calc_stat,
+    RunInfo, TestInfo, Thresholds,
+};
+use crate::session::{Progress, RunEvent, RunHandle, RunResult};
+use crate::tests::{self, FailedBlock, NistWrapper, RandomnessTest, ResultTestsStat, TestStat};
+use crate::{ais31, entropy, fips, gmt};
+
+/// Set of tests and the way their results are judged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BatteryKind {
+    /// NIST SP 800-22: P-values of blocks are checked for proportion and uniformity.
+    #[default]
+    #[serde(rename = "nist")]
+    Nist,
+    /// FIPS 140-2: every block must pass every test.
+    #[serde(rename = "fips140-2")]
+    Fips140_2,
+    /// AIS 31 procedures A and B, each block is an application of the procedures.
+    #[serde(rename = "ais31")]
+    Ais31,
+    /// GM/T 0005-2021: P-values are checked for the minimum pass rate and uniformity.
+    #[serde(rename = "gmt0005")]
+    Gmt0005,
+    /// SP 800-90B min-entropy estimates of raw samples, nothing passes or fails.
+    #[serde(rename = "sp800-90b")]
+    Entropy,
+}
+
+impl BatteryKind {
+    pub const ALL: [BatteryKind; 5] = [
+        BatteryKind::Nist,
+        BatteryKind::Fips140_2,
+        BatteryKind::Ais31,
+        BatteryKind::Gmt0005,
+        BatteryKind::Entropy,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BatteryKind::Nist => "NIST SP 800-22",
+            BatteryKind::Fips140_2 => "FIPS 140-2",
+            BatteryKind::Ais31 => "AIS 31",
+            BatteryKind::Gmt0005 => "GM/T 0005-2021",
+            BatteryKind::Entropy => "SP 800-90B entropy",
+        }
+    }
+
+    /// Name used on the command line and in profiles.
+    pub fn id(self) -> &'static str {
+        match self {
+            BatteryKind::Nist => "nist",
+            BatteryKind::Fips140_2 => "fips140-2",
+            BatteryKind::Ais31 => "ais31",
+            BatteryKind::Gmt0005 => "gmt0005",
+            BatteryKind::Entropy => "sp800-90b",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.id().eq_ignore_ascii_case(id))
+    }
+
+    /// Tests of the battery with default parameters.
+    pub fn tests(self) -> Vec<NistWrapper> {
+        match self {
+            BatteryKind::Nist => tests::nist_tests(),
+            BatteryKind::Fips140_2 => fips::fips_tests(),
+            BatteryKind::Ais31 => ais31::ais31_tests(),
+            BatteryKind::Gmt0005 => gmt::gmt_tests(),
+            BatteryKind::Entropy => entropy::entropy_tests(),
+        }
+    }
+
+    /// Length of a block the tests are designed for.
+    pub fn default_bits(self) -> usize {
+        match self {
+            BatteryKind::Nist => 1_000_000,
+            BatteryKind::Fips140_2 => fips::BLOCK_BITS,
+            BatteryKind::Ais31 => ais31::PROCEDURE_A_BITS,
+            BatteryKind::Gmt0005 => gmt::BLOCK_BITS,
+            BatteryKind::Entropy => entropy::MIN_SAMPLES * u8::BITS as usize,
+        }
+    }
+
+    /// Results are P-values judged by the second-level statistics,
+    /// otherwise every block must pass every test.
+    pub fn uses_p_values(self) -> bool {
+        matches!(self, BatteryKind::Nist | BatteryKind::Gmt0005)
+    }
+
+    /// Results are values reported for every block rather than pass or fail verdicts.
+    pub fn reports_values(self) -> bool {
+        self == BatteryKind::Entropy
+    }
+}
+
+#[derive(Clone)]
+pub struct BatteryConfig {
+    pub kind: BatteryKind,
+    pub n_bits: usize,
+    pub n_blocks: usize,
+    pub tests: Vec<NistWrapper>,
+    pub thresholds: Thresholds,
+    /// SP 800-90B health tests run over the same input, `None` - disabled.
+    pub health: Option<HealthConfig>,
+    /// Encoding of the input, `n_bits` counts decoded bits.
+    pub format: InputFormat,
+    pub bit_order: BitOrder,
+    /// Bytes of words of this size are swapped, 8 - no swapping.
+    pub word_bits: usize,
+    /// Part of the decoded input to test.
+    pub region: Region,
+}
+
+impl BatteryConfig {
+    /// Configuration with all NIST tests enabled and default parameters.
+    pub fn new(n_bits: usize, n_blocks: usize) -> Self {
+        Self {
+            kind: BatteryKind::Nist,
+            n_bits,
+            n_blocks,
+            tests: tests::nist_tests(),
+            thresholds: Thresholds::default(),
+            health: None,
+            format: InputFormat::default(),
+            bit_order: BitOrder::default(),
+            word_bits: u8::BITS as usize,
+            region: Region::default(),
+        }
+    }
+
+    /// Replaces the tests with the tests of `kind`.
+    pub fn with_kind(mut self, kind: BatteryKind) -> Self {
+        self.kind = kind;
+        self.tests = kind.tests();
+        self
+    }
+
+    /// Number of whole blocks in the region of the input of `bits`.
+    pub fn blocks_in(&self, bits: u64) -> usize {
+        self.region.blocks(bits, self.n_bits)
+    }
+
+    /// Adds an enabled test with default parameters to the battery.
+    pub fn register(&mut self, test: impl RandomnessTest + 'static) {
+        self.tests.push(NistWrapper::new(Arc::new(test)));
+    }
+}
+
+pub struct Battery {
+    config: BatteryConfig,
+    input: String,
+}
+
+impl Battery {
+    pub fn new(config: BatteryConfig) -> Self {
+        Self {
+            config,
+            input: String::new(),
+        }
+    }
+
+    /// Sets the name of the input recorded in the report.
+    pub fn with_input(mut self, input: impl Into<String>) -> Self {
+        self.input = input.into();
+        self
+    }
+
+    pub fn config(&self) -> &BatteryConfig {
+        &self.config
+    }
+
+    /// Reads `n_blocks` blocks of `n_bits` from `source` and runs enabled tests on each of them.
+    /// Errors of single tests are passed to `on_error` and don't stop the run.
+    /// Returns `Ok(None)` if the run was stopped with [`Progress::stop`].
+    pub fn run_stat<R: Read>(
+        &self,
+        source: R,
+        progress: &Progress,
+        on_error: &mut dyn FnMut(TestError),
+    ) -> Result<Option<ResultTestsStat>, TestError> {
+        let decoder = Decoder::new(source, self.config.format);
+        let unpacker = Unpacker::new(decoder, self.config.bit_order, self.config.word_bits);
+        let mut reader = BlockReader::new(unpacker, self.config.region);
+        self.run_blocks(&mut reader, progress, on_error)
+    }
+
+    /// Same as [`Battery::run_stat`] with the input already decoded and unpacked.
+    /// The run stops after the last complete block if the input is shorter than `n_blocks`.
+    fn run_blocks<R: Read>(
+        &self,
+        reader: &mut BlockReader<R>,
+        progress: &Progress,
+        on_error: &mut dyn FnMut(TestError),
+    ) -> Result<Option<ResultTestsStat>, TestError> {
+        let mut stat: ResultTestsStat = vec![vec![]; self.config.tests.len()];
+        let p_values = self.config.kind.uses_p_values();
+        let keep_values = self.config.kind.reports_values();
+
+        let all_time = Instant::now();
+        let mut sum_time = Duration::default();
+        for i in 0..self.config.n_blocks {
+            let local_time = Instant::now();
+
+            if progress.is_stopped() {
+                return Ok(None);
+            }
+
+            let data = match reader.read_block(self.config.n_bits) {
+                Ok(Some(data)) => data,
+                Ok(None) => break,
+                Err(e) => {
+                    return Err(TestError::Read {
+                        block: i,
+                        message: e.to_string(),
+                    })
+                }
+            };
+
+            let errors: Vec<TestError> = stat
+                .par_iter_mut()
+                .zip(&self.config.tests)
+                .filter_map(|(ls, nist)| {
+                    if !nist.enable || progress.is_stopped() {
+                        return None;
+                    }
+
+                    match nist.run(&data) {
+                        Ok(v) => {
+                            if ls.len() != v.len() {
+                                let skipped = ls.first().map(TestStat::skipped_like);
+                                ls.resize(v.len(), skipped.unwrap_or_default());
+                            }
+
+                            v.into_iter()
+                                .zip(&mut *ls)
+                                .for_each(|((passed, p_val), st)| {
+                                    let passed = if p_values {
+                                        p_val >= self.config.thresholds.alpha
+                                    } else {
+                                        passed
+                                    };
+
+                                    if passed {
+                                        st.ratio += 1_f64;
+                                    } else {
+                                        st.failed_blocks.push(FailedBlock {
+                                            block: i,
+                                            value: p_val,
+                                        });
+                                    }
+
+                                    if p_values {
+                                        let index = ((p_val * 10_f64).floor() as usize).min(9);
+                                        st.p_distr[index] += 1;
+                                    }
+                                    if keep_values {
+                                        st.values.push(p_val);
+                                    }
+                                    st.samples += 1;
+                                });
+
+                            None
+                        }
+                        Err(e) => {
+                            // The number of subtests is unknown until the first success.
+                            if ls.is_empty() {
+                                ls.push(TestStat::default());
+                            }
+
+                            match e {
+                                Some(e) => {
+                                    ls.iter_mut().for_each(|st| st.errors += 1);
+                                    Some(TestError::Test {
+                                        test: nist.name,
+                                        block: i,
+                                        message: e,
+                                    })
+                                }
+                                None => {
+                                    ls.iter_mut().for_each(|st| st.not_applicable += 1);
+                                    Some(TestError::NotApplicable {
+                                        test: nist.name,
+                                        block: i,
+                                    })
+                                }
+                            }
+                        }
+                    }
+                })
+                .collect();
+
+            errors.into_iter().for_each(&mut *on_error);
+
+            sum_time += local_time.elapsed();
+
+            progress.block_done(sum_time / (i as u32 + 1), all_time.elapsed());
+        }
+
+        if progress.is_stopped() {
+            return Ok(None);
+        }
+
+        Ok(Some(stat))
+    }
+
+    /// Runs the battery and builds the second-level report.
+    /// Returns `Ok(None)` if the run was stopped.
+    pub fn run<R: Read>(&self, source: R) -> RunResult {
+        self.run_with_progress(source, &Progress::new(self.config.n_blocks), &mut |_| {})
+    }
+
+    /// Same as [`Battery::run`], but publishes progress to `progress` and can be stopped with it.
+    /// Errors of single tests are passed to `on_error` as soon as they happen
+    /// and are included in the report.
+    pub fn run_with_progress<R: Read>(
+        &self,
+        source: R,
+        progress: &Progress,
+        on_error: &mut dyn FnMut(&TestError),
+    ) -> RunResult {
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |v| v.as_secs());
+
+        let mut errors = vec![];
+        let mut health = self.config.health.map(HealthTests::new);
+        let decoder = Decoder::new(source, self.config.format);
+        // Health tests see the decoded samples as they are, before unpacking and the region.
+        let tap = Tap::new(decoder, |bytes: &[u8]| {
+            if let Some(health) = &mut health {
+                health.feed(bytes);
+            }
+        });
+        let unpacker = Unpacker::new(tap, self.config.bit_order, self.config.word_bits);
+        let mut reader = BlockReader::new(unpacker, self.config.region);
+        let stat = self.run_blocks(&mut reader, progress, &mut |e| {
+            on_error(&e);
+            errors.push(e);
+        })?;
+        // Decoded bytes up to the word with the end of the last block, the rest was read ahead.
+        let health_end = reader
+            .block_end()
+            .next_multiple_of((self.config.word_bits / u8::BITS as usize) as u64);
+
+        let info = RunInfo {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            battery: self.config.kind,
+            input: self.input.clone(),
+            format: reader.get_ref().get_ref().get_ref().format(),
+            bit_order: self.config.bit_order,
+            word_bits: self.config.word_bits,
+            region: self.config.region,
+            started_at,
+            duration_ms: progress.total_time().as_millis() as u64,
+            n_bits: self.config.n_bits,
+            n_blocks: self.config.n_blocks,
+            blocks_read: reader.blocks_read(),
+            bits_read: reader.bits_read(),
+            thresholds: self.config.thresholds,
+            tests: self
+                .config
+                .tests
+                .iter()
+                .filter(|test| test.enable)
+                .map(|test| TestInfo {
+                    name: test.name,
+                    params: test.params.iter().map(|p| (p.spec.name, p.value)).collect(),
+                })
+                .collect(),
+        };
+
+        let calc = match self.config.kind {
+            BatteryKind::Nist => calc_stat,
+            BatteryKind::Fips140_2 => calc_pass_stat,
+            BatteryKind::Ais31 => calc_ais_stat,
+            BatteryKind::Gmt0005 => calc_gmt_stat,
+            BatteryKind::Entropy => calc_entropy_stat,
+        };
+
+        Ok(stat.map(|stat| {
+            let mut report = calc(&self.config.tests, stat, errors, info);
+            if let Some(health) = health {
+                add_health(&mut report, health.finish_at(health_end));
+            }
+            report
+        }))
+    }
+}
+
+fn thread_test(
+    path: String,
+    config: BatteryConfig,
+    progress: &Progress,
+    sender: &Sender<RunEvent>,
+) -> RunResult {
+    let (source, input): (Box<dyn Read>, String) = if path == input::STDIN {
+        (Box::new(io::stdin()), "stdin".to_string())
+    } else {
+        let file = File::open(&path).map_err(|e| TestError::Open {
+            path: path.clone(),
+            message: e.to_string(),
+        })?;
+        (Box::new(file), path)
+    };
+
+    Battery::new(config)
+        .with_input(input)
+        .run_with_progress(source, progress, &mut |e| {
+            let _ = sender.send(RunEvent::Error(e.clone()));
+        })
+}
+
+/// Runs the battery over the file `path` in a background thread, [`input::STDIN`] reads the standard input.
+pub fn start_thread(path: String, config: BatteryConfig) -> RunHandle {
+    let (sender, receiver) = channel::<RunEvent>();
+    let progress = Arc::new(Progress::new(config.n_blocks));
+
+    let thread_progress = progress.clone();
+    std::thread::spawn(move || {
+        let result = thread_test(path, config, &thread_progress, &sender);
+        // The handle may be dropped before the run is finished.
+        let _ = sender.send(RunEvent::Finished(Box::new(result)));
+    });
+
+    RunHandle::new(progress, receiver)
+}
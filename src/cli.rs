@@ -0,0 +1,468 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use guinistrs::export::{self, ExportFormat};
+use guinistrs::input::{self, BitOrder, InputFormat};
+use guinistrs::presets;
+use guinistrs::tests::NistWrapper;
+use guinistrs::validate;
+use guinistrs::{start_thread, BatteryConfig, BatteryKind, Profile, RunEvent};
+
+const USAGE: &str = "Usage: GuiNistRs run <FILE> [--bits N] [--blocks N] [--tests NAME[,NAME...]]
+
+FILE can be a regular file, a named pipe, a device such as /dev/hwrng or - for
+the standard input; the size of a stream is unknown, so --blocks is required.
+
+Options:
+    --battery NAME
+                  Battery of tests: nist (default), fips140-2, ais31, gmt0005
+                  or sp800-90b (min-entropy estimates)
+    --format NAME Format of the input: raw (default), auto (detected from
+                  the contents), ascii ('0' and '1' characters), hex or
+                  base64; whitespace in text is ignored
+    --bit-order ORDER
+                  Order of bits in a byte: msb (default) or lsb
+    --word-bits N Swap bytes of words of N bits: 8 (default, no swapping),
+                  16, 32 or 64, e.g. for little-endian words of a generator
+    --offset SIZE Skip SIZE at the start of the decoded input; SIZE is in
+                  bytes or in bits with the suffix 'bits', e.g. 100bits
+    --length SIZE Test at most SIZE after the offset (default: up to the end)
+    --skip SIZE   Skip SIZE after every block
+    --profile FILE
+                  Load settings saved in the Configure window, the options
+                  below override them
+    --preset NAME Apply a built-in or a saved preset before the profile
+    --presets     Print the names of available presets
+    --bits N      Number of decoded bits in one block (default: 1000000,
+                  20000 for fips140-2, 8285728 for ais31, 8000000 for sp800-90b)
+    --blocks N    Number of blocks to test (default: whole file); the run
+                  stops after the last complete block if the input is shorter
+    --tests LIST  Comma separated list of tests to run (default: all)
+    --alpha A     Significance level of a single test (default: 0.01)
+    --uniformity P
+                  Minimum P-value of the uniformity of P-values (default: 0.0001)
+    --sigma S     Width of the proportion range in sigmas (default: 3)
+    --output PATH Write the text report to PATH, a file or a directory
+    --timestamp   Add the time of the run to the name of the text report
+                  written with --output
+    --export PATH Also write the report to PATH, the format is chosen by
+                  the extension: .json, .csv or text (can be repeated)
+    --overwrite   Overwrite existing report files
+    --health H    Also run the SP 800-90B health tests (repetition count and
+                  adaptive proportion) over the input with the claimed
+                  min-entropy of H bits per sample
+    --sample-bits N
+                  Width of a sample stored in each byte for the health tests
+                  (default: 8)
+    --preflight MODE
+                  What to do with tests whose parameters don't fit the block
+                  size: warn (default), adjust, disable or fail
+    --list        Print the names of tests of the battery
+
+Exit codes:
+    0             All tests passed
+    1             Some tests or health tests failed
+    2             Invalid arguments or the input can't be read
+    3             Nothing failed, but the run is incomplete: some tests weren't
+                  evaluated or had errors, or a file ended before --blocks blocks";
+
+struct Args {
+    path: String,
+    config: BatteryConfig,
+    export: Vec<PathBuf>,
+    output: Option<String>,
+    timestamp: bool,
+    overwrite: bool,
+    preflight: Preflight,
+}
+
+/// Handling of the issues found by [`validate::validate`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Preflight {
+    Warn,
+    Adjust,
+    Disable,
+    Fail,
+}
+
+impl FromStr for Preflight {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "warn" => Ok(Self::Warn),
+            "adjust" => Ok(Self::Adjust),
+            "disable" => Ok(Self::Disable),
+            "fail" => Ok(Self::Fail),
+            v => Err(format!("unknown mode {}", v)),
+        }
+    }
+}
+
+fn parse_value<T>(name: &str, value: Option<&String>) -> Result<T, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = value.ok_or(format!("Missing value for {}", name))?;
+    value
+        .parse::<T>()
+        .map_err(|e| format!("Invalid value for {}: {}", name, e))
+}
+
+/// Size in bytes or in bits with the suffix "bits", returns bits.
+fn parse_size(name: &str, value: Option<&String>) -> Result<u64, String> {
+    let value = value.ok_or(format!("Missing value for {}", name))?;
+    let (number, scale) = match value.strip_suffix("bits") {
+        Some(v) => (v, 1),
+        None => (value.as_str(), u8::BITS as u64),
+    };
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|v| v.checked_mul(scale))
+        .ok_or(format!("Invalid value for {}: {}", name, value))
+}
+
+fn parse_battery(name: &str) -> Result<BatteryKind, String> {
+    BatteryKind::from_id(name).ok_or(format!("Unknown battery: {}", name))
+}
+
+fn parse_args(args: &[String]) -> Result<Args, String> {
+    let mut path = None;
+    let mut battery = None;
+    let mut profile = None;
+    let mut preset = None;
+    let mut n_bits = None;
+    let mut n_blocks = None;
+    let mut tests: Option<Vec<String>> = None;
+    let mut alpha = None;
+    let mut uniformity = None;
+    let mut sigma = None;
+    let mut export = vec![];
+    let mut output = None;
+    let mut timestamp = false;
+    let mut overwrite = false;
+    let mut preflight = Preflight::Warn;
+    let mut health_entropy = None;
+    let mut sample_bits = None;
+    let mut format = None;
+    let mut bit_order = None;
+    let mut word_bits = None;
+    let mut offset = None;
+    let mut length = None;
+    let mut skip = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--battery" => {
+                let name = iter.next().ok_or("Missing value for --battery")?;
+                battery = Some(parse_battery(name)?);
+            }
+            "--profile" => {
+                let path = iter.next().ok_or("Missing value for --profile")?;
+                profile = Some(PathBuf::from(path));
+            }
+            "--preset" => {
+                let name = iter.next().ok_or("Missing value for --preset")?;
+                preset = Some(name.clone());
+            }
+            "--bits" => n_bits = Some(parse_value(arg, iter.next())?),
+            "--blocks" => n_blocks = Some(parse_value(arg, iter.next())?),
+            "--alpha" => alpha = Some(parse_value(arg, iter.next())?),
+            "--uniformity" => uniformity = Some(parse_value(arg, iter.next())?),
+            "--sigma" => sigma = Some(parse_value(arg, iter.next())?),
+            "--output" => {
+                let path = iter.next().ok_or("Missing value for --output")?;
+                output = Some(path.clone());
+            }
+            "--timestamp" => timestamp = true,
+            "--overwrite" => overwrite = true,
+            "--preflight" => preflight = parse_value(arg, iter.next())?,
+            "--format" => {
+                let name = iter.next().ok_or("Missing value for --format")?;
+                format =
+                    Some(InputFormat::from_id(name).ok_or(format!("Unknown format: {}", name))?);
+            }
+            "--bit-order" => {
+                let name = iter.next().ok_or("Missing value for --bit-order")?;
+                bit_order =
+                    Some(BitOrder::from_id(name).ok_or(format!("Unknown bit order: {}", name))?);
+            }
+            "--word-bits" => word_bits = Some(parse_value(arg, iter.next())?),
+            "--offset" => offset = Some(parse_size(arg, iter.next())?),
+            "--length" => length = Some(parse_size(arg, iter.next())?),
+            "--skip" => skip = Some(parse_size(arg, iter.next())?),
+            "--health" => health_entropy = Some(parse_value(arg, iter.next())?),
+            "--sample-bits" => sample_bits = Some(parse_value(arg, iter.next())?),
+            "--export" => {
+                let path = iter.next().ok_or("Missing value for --export")?;
+                export.push(PathBuf::from(path));
+            }
+            "--tests" => {
+                let list = iter.next().ok_or("Missing value for --tests")?;
+                tests = Some(list.split(',').map(|s| s.trim().to_string()).collect());
+            }
+            v if v.starts_with("--") => return Err(format!("Unknown option: {}", v)),
+            v => {
+                if path.replace(v.to_string()).is_some() {
+                    return Err(format!("Unexpected argument: {}", v));
+                }
+            }
+        }
+    }
+
+    // 0 blocks - test the whole file.
+    let kind = battery.unwrap_or_default();
+    let mut config = BatteryConfig::new(kind.default_bits(), 0).with_kind(kind);
+
+    if let Some(name) = preset {
+        let preset = presets::find_preset(&name).ok_or(format!("Unknown preset: {}", name))?;
+        preset.profile.apply(&mut config)?;
+    }
+
+    if let Some(path) = profile {
+        let profile = Profile::load(&path)
+            .map_err(|e| format!("Can't load profile {}: {}", path.display(), e))?;
+        profile.apply(&mut config)?;
+    }
+
+    if let Some(v) = n_bits {
+        config.n_bits = v;
+    }
+    if let Some(v) = n_blocks {
+        config.n_blocks = v;
+    }
+    if let Some(v) = alpha {
+        config.thresholds.alpha = v;
+    }
+    if let Some(v) = uniformity {
+        config.thresholds.uniformity = v;
+    }
+    if let Some(v) = sigma {
+        config.thresholds.proportion_sigma = v;
+    }
+    if let Some(names) = tests {
+        select_tests(&mut config.tests, &names)?;
+    }
+    if let Some(v) = format {
+        config.format = v;
+    }
+    if let Some(v) = bit_order {
+        config.bit_order = v;
+    }
+    if let Some(v) = word_bits {
+        input::check_word_bits(v)?;
+        config.word_bits = v;
+    }
+    config.region.offset = offset.unwrap_or(config.region.offset);
+    config.region.length = length.or(config.region.length);
+    config.region.skip = skip.unwrap_or(config.region.skip);
+    if health_entropy.is_some() || sample_bits.is_some() {
+        let mut health = config.health.unwrap_or_default();
+        health.entropy = health_entropy.unwrap_or(health.entropy);
+        health.sample_bits = sample_bits.unwrap_or(health.sample_bits);
+        config.health = Some(health);
+    }
+    if let Some(health) = &config.health {
+        health.check()?;
+    }
+
+    if config.n_bits < u8::BITS as usize {
+        return Err("Number of bits must be at least 8".to_string());
+    }
+
+    if !(config.thresholds.alpha > 0_f64 && config.thresholds.alpha < 1_f64) {
+        return Err("Alpha must be in (0, 1)".to_string());
+    }
+
+    if timestamp && output.is_none() {
+        return Err("--timestamp requires --output".to_string());
+    }
+
+    Ok(Args {
+        path: path.ok_or("Missing input file")?,
+        config,
+        export,
+        output,
+        timestamp,
+        overwrite,
+        preflight,
+    })
+}
+
+fn select_tests(tests: &mut [NistWrapper], names: &[String]) -> Result<(), String> {
+    for name in names {
+        if !tests
+            .iter()
+            .any(|test| test.name.eq_ignore_ascii_case(name))
+        {
+            return Err(format!("Unknown test: {}", name));
+        }
+    }
+
+    tests.iter_mut().for_each(|test| {
+        test.enable = names
+            .iter()
+            .any(|name| test.name.eq_ignore_ascii_case(name));
+    });
+
+    Ok(())
+}
+
+/// Runs the battery without the GUI and returns the process exit code:
+/// 0 - all tests passed, 1 - some tests or health tests failed, 2 - invalid arguments or input,
+/// 3 - nothing failed, but some tests weren't evaluated or had errors or the file ended early.
+pub fn run(args: &[String]) -> i32 {
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return 0;
+    }
+
+    if args.iter().any(|arg| arg == "--presets") {
+        presets::all_presets()
+            .iter()
+            .for_each(|preset| println!("{}", preset.name));
+        return 0;
+    }
+
+    if args.iter().any(|arg| arg == "--list") {
+        let battery = match args.iter().position(|arg| arg == "--battery") {
+            Some(i) => args
+                .get(i + 1)
+                .map_or(Ok(BatteryKind::default()), |v| parse_battery(v)),
+            None => Ok(BatteryKind::default()),
+        };
+
+        match battery {
+            Ok(kind) => kind
+                .tests()
+                .iter()
+                .for_each(|test| println!("{}", test.name)),
+            Err(e) => {
+                eprintln!("{}", e);
+                return 2;
+            }
+        }
+        return 0;
+    }
+
+    let mut args = match parse_args(args) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return 2;
+        }
+    };
+
+    let path = Path::new(&args.path);
+    let stream = input::is_stream(path);
+    if args.config.n_blocks == 0 {
+        if stream {
+            eprintln!(
+                "The size of {} is unknown, set the number of blocks with --blocks",
+                args.path
+            );
+            return 2;
+        }
+        args.config.n_blocks =
+            match input::input_bits(path, args.config.format, args.config.word_bits) {
+                Ok(v) => args.config.blocks_in(v),
+                Err(e) => {
+                    eprintln!("Can't read input: {}", e);
+                    return 2;
+                }
+            };
+    }
+
+    if args.config.n_blocks == 0 {
+        eprintln!("Nothing to test: the input region is shorter than one block");
+        return 2;
+    }
+
+    let issues = validate::validate(args.config.kind, &args.config.tests, args.config.n_bits);
+    if !issues.is_empty() {
+        let action = match args.preflight {
+            Preflight::Warn => "warning",
+            Preflight::Adjust => "adjusted",
+            Preflight::Disable => "disabled",
+            Preflight::Fail => "error",
+        };
+        issues
+            .iter()
+            .for_each(|issue| eprintln!("{}: {}", action, issue));
+
+        match args.preflight {
+            Preflight::Warn => {}
+            Preflight::Adjust => validate::apply_fixes(&mut args.config.tests, &issues, false),
+            Preflight::Disable => validate::apply_fixes(&mut args.config.tests, &issues, true),
+            Preflight::Fail => return 2,
+        }
+    }
+
+    let run = start_thread(args.path, args.config);
+
+    let result = loop {
+        match run.poll(Duration::from_millis(500)) {
+            Some(RunEvent::Finished(v)) => break *v,
+            // Errors of single tests are printed with the report.
+            Some(RunEvent::Error(_)) => {}
+            None => {
+                let progress = run.progress();
+                eprint!(
+                    "\rBlocks: {}/{}, time left: {} s  ",
+                    progress.complete_blocks(),
+                    progress.n_blocks(),
+                    progress.time_left().as_secs()
+                );
+            }
+        }
+    };
+    eprintln!();
+
+    match result {
+        Ok(Some(report)) => {
+            println!("{}", report.text);
+
+            let mut files: Vec<_> = args
+                .export
+                .iter()
+                .map(|path| (path.clone(), ExportFormat::from_path(path)))
+                .collect();
+
+            if let Some(output) = &args.output {
+                let started_at = args.timestamp.then_some(report.info.started_at);
+                let path = export::report_path(&report.info.input, output, started_at);
+                files.push((path, ExportFormat::Text));
+            }
+
+            for (path, format) in files {
+                if let Err(e) = export::export(&report, &path, format, args.overwrite) {
+                    eprintln!("Can't write report to {}: {}", path.display(), e);
+                    return 2;
+                }
+                eprintln!("Report saved to {}", path.display());
+            }
+
+            let health_failed = report.health.as_ref().is_some_and(|v| !v.passed());
+            // A stream may end at any time, a file is expected to hold the requested blocks.
+            let ended = !stream && report.info.blocks_read < report.info.n_blocks;
+            if report.failed > 0 || health_failed {
+                1
+            } else if report.incomplete > 0 || !report.errors.is_empty() || ended {
+                3
+            } else {
+                0
+            }
+        }
+        Ok(None) => 2,
+        Err(e) => {
+            eprintln!("{}", e);
+            2
+        }
+    }
+}
@@ -1,16 +1,267 @@
 use egui_extras::{Size, StripBuilder};
 
-use crate::tests::*;
+use native_dialog::{FileDialog, MessageDialog, MessageType};
 
-#[derive(Default)]
-pub struct ConfigureTests {}
+use guinistrs::health::HealthConfig;
+use guinistrs::presets::{self, Preset};
+use guinistrs::tests::*;
+use guinistrs::{BatteryConfig, BatteryKind, Profile, Thresholds};
+
+pub struct ConfigureTests {
+    pub battery: BatteryKind,
+    /// Tests of the battery with their settings.
+    pub tests: Vec<NistWrapper>,
+    pub thresholds: Thresholds,
+    health: HealthConfig,
+    health_enabled: bool,
+    presets: Vec<Preset>,
+    selected_preset: usize,
+    preset_name: String,
+}
+
+impl Default for ConfigureTests {
+    fn default() -> Self {
+        Self {
+            battery: BatteryKind::default(),
+            tests: BatteryKind::default().tests(),
+            thresholds: Thresholds::default(),
+            health: HealthConfig::default(),
+            health_enabled: false,
+            presets: presets::all_presets(),
+            selected_preset: 0,
+            preset_name: String::new(),
+        }
+    }
+}
+
+/// Editor of a parameter chosen by its type.
+fn build_param_ui(ui: &mut egui::Ui, param: &mut TestParam) {
+    ui.label(param.spec.name)
+        .on_hover_text(param.spec.description);
+
+    let response = match (param.spec.kind, &mut param.value) {
+        (ParamKind::Int { min, max }, ParamValue::Int(value)) => {
+            ui.add(egui::DragValue::new(value).clamp_range(min..=max))
+        }
+        (ParamKind::IntOrAuto { min, max }, ParamValue::Int(value)) => {
+            ui.horizontal(|ui| {
+                let mut auto = *value == 0;
+                let response = ui.checkbox(&mut auto, "auto");
+                if response.changed() {
+                    *value = if auto { 0 } else { min };
+                }
+                if !auto {
+                    ui.add(egui::DragValue::new(value).clamp_range(min..=max));
+                }
+                response
+            })
+            .inner
+        }
+        (ParamKind::Bool, ParamValue::Bool(value)) => ui.checkbox(value, ""),
+        _ => return,
+    };
+    response.on_hover_text(param.spec.description);
+}
+
+fn show_error(text: &str) {
+    MessageDialog::new()
+        .set_type(MessageType::Error)
+        .set_title("Error!")
+        .set_text(text)
+        .show_alert()
+        .unwrap();
+}
 
 impl ConfigureTests {
+    /// Configuration of a run with the tests set in the window,
+    /// the size of blocks and the input settings are taken from `run`.
+    pub fn config(&self, run: &BatteryConfig) -> BatteryConfig {
+        BatteryConfig {
+            kind: self.battery,
+            tests: self.tests.clone(),
+            thresholds: self.thresholds,
+            health: self.health_enabled.then_some(self.health),
+            ..run.clone()
+        }
+    }
+
+    fn profile(&self, run: &BatteryConfig) -> Profile {
+        Profile::from_config(&self.config(run))
+    }
+
+    fn apply_profile(&mut self, profile: &Profile, run: &mut BatteryConfig) -> Result<(), String> {
+        let mut config = self.config(run);
+        profile.apply(&mut config)?;
+
+        self.tests = std::mem::take(&mut config.tests);
+        self.battery = config.kind;
+        self.thresholds = config.thresholds;
+        self.health_enabled = config.health.is_some();
+        self.health = config.health.unwrap_or(self.health);
+        *run = config;
+
+        Ok(())
+    }
+
+    fn save_profile(&self, run: &BatteryConfig) {
+        let path = FileDialog::new()
+            .add_filter("Profile", &["json"])
+            .show_save_single_file()
+            .unwrap();
+
+        if let Some(path) = path {
+            if let Err(e) = self.profile(run).save(&path) {
+                show_error(&format!("Can't save profile: {}", e));
+            }
+        }
+    }
+
+    fn load_profile(&mut self, run: &mut BatteryConfig) {
+        let path = FileDialog::new()
+            .add_filter("Profile", &["json"])
+            .show_open_single_file()
+            .unwrap();
+
+        if let Some(path) = path {
+            let profile = match Profile::load(&path) {
+                Ok(v) => v,
+                Err(e) => {
+                    show_error(&format!("Can't load profile: {}", e));
+                    return;
+                }
+            };
+
+            if let Err(e) = self.apply_profile(&profile, run) {
+                show_error(&format!("Can't load profile: {}", e));
+            }
+        }
+    }
+
+    fn build_battery_ui(&mut self, ui: &mut egui::Ui, run: &mut BatteryConfig) {
+        ui.horizontal(|ui| {
+            ui.label("Battery: ");
+            let mut battery = self.battery;
+            egui::ComboBox::from_id_source("battery")
+                .selected_text(battery.name())
+                .show_ui(ui, |ui| {
+                    BatteryKind::ALL.into_iter().for_each(|kind| {
+                        ui.selectable_value(&mut battery, kind, kind.name());
+                    });
+                });
+
+            if battery != self.battery {
+                self.battery = battery;
+                self.tests = battery.tests();
+                run.n_bits = battery.default_bits();
+            }
+        });
+    }
+
+    fn build_presets_ui(&mut self, ui: &mut egui::Ui, run: &mut BatteryConfig) {
+        ui.horizontal(|ui| {
+            ui.label("Preset: ");
+            let selected = self
+                .presets
+                .get(self.selected_preset)
+                .map_or("", |preset| preset.name.as_str());
+            egui::ComboBox::from_id_source("preset")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    self.presets.iter().enumerate().for_each(|(i, preset)| {
+                        ui.selectable_value(&mut self.selected_preset, i, &preset.name);
+                    });
+                });
+
+            if ui.button("Apply").clicked() {
+                if let Some(preset) = self.presets.get(self.selected_preset).cloned() {
+                    if let Err(e) = self.apply_profile(&preset.profile, run) {
+                        show_error(&format!("Can't apply preset: {}", e));
+                    }
+                }
+            }
+
+            ui.add(egui::TextEdit::singleline(&mut self.preset_name).hint_text("name"));
+            if ui.button("Save as preset").clicked() {
+                let profile = self.profile(run);
+                match presets::save_user_preset(&self.preset_name, &profile) {
+                    Ok(_) => {
+                        self.presets = presets::all_presets();
+                        self.selected_preset = self
+                            .presets
+                            .iter()
+                            .position(|preset| preset.name == self.preset_name.trim())
+                            .unwrap_or_default();
+                        self.preset_name.clear();
+                    }
+                    Err(e) => show_error(&format!("Can't save preset: {}", e)),
+                }
+            }
+        });
+    }
+
+    fn build_profile_ui(&mut self, ui: &mut egui::Ui, run: &mut BatteryConfig) {
+        ui.horizontal(|ui| {
+            if ui.button("Save profile...").clicked() {
+                self.save_profile(run);
+            }
+
+            if ui.button("Load profile...").clicked() {
+                self.load_profile(run);
+            }
+        });
+    }
+
+    fn build_thresholds_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Alpha: ");
+            ui.add(
+                egui::DragValue::new(&mut self.thresholds.alpha)
+                    .speed(0.001)
+                    .clamp_range(0.0001..=0.5),
+            );
+
+            ui.label("Uniformity: ");
+            ui.add(
+                egui::DragValue::new(&mut self.thresholds.uniformity)
+                    .speed(0.00001)
+                    .clamp_range(0.0..=1.0),
+            );
+
+            ui.label("Proportion sigma: ");
+            ui.add(
+                egui::DragValue::new(&mut self.thresholds.proportion_sigma)
+                    .speed(0.1)
+                    .clamp_range(1.0..=6.0),
+            );
+        });
+    }
+
+    fn build_health_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.health_enabled, "Health tests")
+                .on_hover_text("SP 800-90B repetition count and adaptive proportion tests");
+
+            ui.add_enabled_ui(self.health_enabled, |ui| {
+                ui.label("Claimed entropy: ");
+                ui.add(
+                    egui::DragValue::new(&mut self.health.entropy)
+                        .speed(0.01)
+                        .clamp_range(0.01..=self.health.sample_bits as f64),
+                );
+
+                ui.label("Sample bits: ");
+                ui.add(egui::DragValue::new(&mut self.health.sample_bits).clamp_range(1..=8));
+            });
+            self.health.entropy = self.health.entropy.min(self.health.sample_bits as f64);
+        });
+    }
+
     fn build_enbale_test_ui(&mut self, builder: StripBuilder<'_>) {
+        let count = self.tests.len();
         builder
-            .sizes(Size::remainder(), NUMBER_OF_TEST)
+            .sizes(Size::remainder(), count)
             .vertical(|mut strip| {
-                TESTS.lock().unwrap().iter_mut().for_each(|test| {
+                self.tests.iter_mut().for_each(|test| {
                     strip.cell(|ui| {
                         ui.checkbox(&mut test.enable, test.name);
                     })
@@ -19,29 +270,41 @@ impl ConfigureTests {
     }
 
     fn build_params_test_ui(&mut self, builder: StripBuilder<'_>) {
+        let count = self.tests.len();
         builder
-            .sizes(Size::remainder(), NUMBER_OF_TEST)
+            .sizes(Size::remainder(), count)
             .vertical(|mut strip| {
-                TESTS.lock().unwrap().iter_mut().for_each(|test| {
-                    if let Some(param) = &mut test.param {
-                        strip.cell(|ui| {
-                            ui.horizontal(|ui| {
-                                ui.label(test.name);
-                                ui.add(
-                                    egui::DragValue::new(&mut param.value)
-                                        .clamp_range(param._min_value..=param._max_value),
-                                );
-                            });
-                        });
+                self.tests.iter_mut().for_each(|test| {
+                    if test.params.is_empty() {
+                        return;
                     }
+
+                    strip.cell(|ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(test.name);
+                            test.params
+                                .iter_mut()
+                                .for_each(|param| build_param_ui(ui, param));
+                        });
+                    });
                 });
             });
     }
 
-    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) {
+    /// The size of blocks and the input settings of `run` are saved to and loaded from profiles.
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool, run: &mut BatteryConfig) {
         egui::Window::new("Configure tests")
             .open(open)
             .show(ctx, |ui| {
+                self.build_battery_ui(ui, run);
+                self.build_presets_ui(ui, run);
+                self.build_profile_ui(ui, run);
+                ui.separator();
+
+                self.build_thresholds_ui(ui);
+                self.build_health_ui(ui);
+                ui.separator();
+
                 StripBuilder::new(ui)
                     .sizes(Size::remainder(), 2)
                     .horizontal(|mut strip| {
@@ -0,0 +1,836 @@
+//! SP 800-90B min-entropy estimators (section 6.3) for raw samples of a noise source.
+//!
+//! Samples are stored one per byte, a sample of `w` bits is the lowest `w` bits of the byte.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use nistrs::{BitsData, TestResultT};
+
+use crate::tests::{NistWrapper, ParamKind, ParamSpec, ParamValue, RandomnessTest, TestParam};
+
+/// Quantile of the normal distribution for the 99% upper bound of probabilities.
+const Z_ALPHA: f64 = 2.576;
+
+/// Number of samples recommended by SP 800-90B.
+pub const MIN_SAMPLES: usize = 1_000_000;
+
+/// The bitstring of non-binary samples is truncated to this length, as in the NIST reference tool.
+const MAX_BITSTRING: usize = 1_000_000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Estimator {
+    MostCommonValue,
+    Collision,
+    Markov,
+    Compression,
+    TTuple,
+    Lrs,
+    MultiMcw,
+    Lag,
+    MultiMmc,
+    Lz78y,
+}
+
+const ESTIMATORS: [Estimator; 10] = [
+    Estimator::MostCommonValue,
+    Estimator::Collision,
+    Estimator::Markov,
+    Estimator::Compression,
+    Estimator::TTuple,
+    Estimator::Lrs,
+    Estimator::MultiMcw,
+    Estimator::Lag,
+    Estimator::MultiMmc,
+    Estimator::Lz78y,
+];
+
+impl Estimator {
+    fn name(self) -> &'static str {
+        match self {
+            Estimator::MostCommonValue => "MostCommonValue",
+            Estimator::Collision => "Collision",
+            Estimator::Markov => "Markov",
+            Estimator::Compression => "Compression",
+            Estimator::TTuple => "TTuple",
+            Estimator::Lrs => "LRS",
+            Estimator::MultiMcw => "MultiMCW",
+            Estimator::Lag => "Lag",
+            Estimator::MultiMmc => "MultiMMC",
+            Estimator::Lz78y => "LZ78Y",
+        }
+    }
+
+    /// Collision, Markov and compression estimates are defined for binary samples only.
+    fn binary_only(self) -> bool {
+        matches!(
+            self,
+            Estimator::Collision | Estimator::Markov | Estimator::Compression
+        )
+    }
+}
+
+/// Min-entropy of the most likely sample with the probability `p`, without the negative zero of `p = 1`.
+fn min_entropy(p: f64) -> f64 {
+    0_f64 - p.log2()
+}
+
+/// 99% upper bound of the probability `p` estimated from `n` observations.
+fn upper_bound(p: f64, n: usize) -> f64 {
+    (p + Z_ALPHA * (p * (1_f64 - p) / (n - 1) as f64).sqrt()).min(1_f64)
+}
+
+/// Most common value estimate (6.3.1).
+fn most_common_value(s: &[u8]) -> f64 {
+    let mut counts = [0_usize; 256];
+    s.iter().for_each(|v| counts[*v as usize] += 1);
+    let p = *counts.iter().max().unwrap_or(&0) as f64 / s.len() as f64;
+    min_entropy(upper_bound(p, s.len()))
+}
+
+/// Collision estimate (6.3.2). For binary samples the expected time to the first collision
+/// is `3 - p^2 - q^2`, so the equation of step 8 is solved directly.
+fn collision(s: &[u8]) -> f64 {
+    let mut times = vec![];
+    let mut i = 0;
+    while i + 2 < s.len() {
+        let t = if s[i] == s[i + 1] { 2 } else { 3 };
+        times.push(t as f64);
+        i += t;
+    }
+
+    let v = times.len() as f64;
+    let mean = times.iter().sum::<f64>() / v;
+    let sigma = (times.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / (v - 1_f64)).sqrt();
+    let mean = mean - Z_ALPHA * sigma / v.sqrt();
+
+    // p^2 + q^2 = 3 - mean, p >= 1/2.
+    let sum_sq = 3_f64 - mean;
+    if sum_sq <= 0.5 {
+        return 1_f64;
+    }
+    let p = ((1_f64 + (2_f64 * sum_sq - 1_f64).sqrt()) / 2_f64).min(1_f64);
+    min_entropy(p)
+}
+
+/// Markov estimate (6.3.3): the most likely sequence of 128 bits.
+fn markov(s: &[u8]) -> f64 {
+    let n = s.len() as f64;
+    let ones = s.iter().filter(|v| **v == 1).count() as f64;
+    let p = [1_f64 - ones / n, ones / n];
+
+    let mut o = [[0_f64; 2]; 2];
+    s.windows(2)
+        .for_each(|w| o[w[0] as usize][w[1] as usize] += 1_f64);
+    let t = |a: usize, b: usize| {
+        let total = o[a][0] + o[a][1];
+        if total > 0_f64 {
+            (o[a][b] / total).log2()
+        } else {
+            f64::NEG_INFINITY
+        }
+    };
+
+    let log_p_max = [
+        p[0].log2() + 127_f64 * t(0, 0),
+        p[0].log2() + 64_f64 * t(0, 1) + 63_f64 * t(1, 0),
+        p[0].log2() + t(0, 1) + 126_f64 * t(1, 1),
+        p[1].log2() + t(1, 0) + 126_f64 * t(0, 0),
+        p[1].log2() + 64_f64 * t(1, 0) + 63_f64 * t(0, 1),
+        p[1].log2() + 127_f64 * t(1, 1),
+    ]
+    .into_iter()
+    .fold(f64::NEG_INFINITY, f64::max);
+
+    ((0_f64 - log_p_max) / 128_f64).min(1_f64)
+}
+
+/// Compression estimate (6.3.4) with blocks of 6 bits and a dictionary of 1000 blocks.
+fn compression(s: &[u8]) -> Option<f64> {
+    const B: usize = 6;
+    const D: usize = 1000;
+
+    let blocks: Vec<usize> = s
+        .chunks_exact(B)
+        .map(|v| v.iter().fold(0, |acc, bit| (acc << 1) | *bit as usize))
+        .collect();
+    let n = blocks.len();
+    if n < D + 2 {
+        return None;
+    }
+    let nu = (n - D) as f64;
+
+    let mut dict = [0_usize; 1 << B];
+    blocks[..D]
+        .iter()
+        .enumerate()
+        .for_each(|(i, v)| dict[*v] = i + 1);
+
+    let mut sum = 0_f64;
+    let mut sum_sq = 0_f64;
+    (D + 1..=n).for_each(|i| {
+        let v = blocks[i - 1];
+        let dist = if dict[v] != 0 { i - dict[v] } else { i };
+        dict[v] = i;
+
+        let log = (dist as f64).log2();
+        sum += log;
+        sum_sq += log * log;
+    });
+
+    let mean = sum / nu;
+    let sigma = 0.5907 * (sum_sq / (nu - 1_f64) - mean * mean).max(0_f64).sqrt();
+    let mean = mean - Z_ALPHA * sigma / nu.sqrt();
+
+    // Expected value of log2 of the distance for a block with the probability z.
+    let logs: Vec<f64> = (0..=n).map(|u| (u as f64).log2()).collect();
+    let g = |z: f64| {
+        let mut sum = 0_f64;
+        let mut pow = 1_f64;
+        // The remaining terms are negligible once the power underflows.
+        for (u, log) in logs.iter().enumerate().skip(1) {
+            if pow < f64::MIN_POSITIVE {
+                break;
+            }
+            if u < n {
+                sum += *log * z * z * pow * (n - D.max(u)) as f64;
+            }
+            if u > D {
+                sum += *log * z * pow;
+            }
+            pow *= 1_f64 - z;
+        }
+        sum / nu
+    };
+
+    let others = ((1 << B) - 1) as f64;
+    let expected = |p: f64| g(p) + others * g((1_f64 - p) / others);
+
+    let (mut lo, mut hi) = (1_f64 / (1 << B) as f64, 1_f64);
+    if expected(lo) <= mean {
+        return Some(1_f64);
+    }
+    for _ in 0..64 {
+        let mid = (lo + hi) / 2_f64;
+        if expected(mid) > mean {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    Some(min_entropy(lo) / B as f64)
+}
+
+/// Suffix array built by prefix doubling.
+fn suffix_array(s: &[u8]) -> Vec<usize> {
+    let n = s.len();
+    let mut sa: Vec<usize> = (0..n).collect();
+    let mut rank: Vec<usize> = s.iter().map(|v| *v as usize).collect();
+    let mut next = vec![0_usize; n];
+
+    let mut k = 1;
+    loop {
+        let key = |i: usize| (rank[i], rank.get(i + k).map_or(0, |r| r + 1));
+        sa.sort_unstable_by_key(|i| key(*i));
+
+        next[sa[0]] = 0;
+        for i in 1..n {
+            next[sa[i]] = next[sa[i - 1]] + (key(sa[i - 1]) != key(sa[i])) as usize;
+        }
+        std::mem::swap(&mut rank, &mut next);
+
+        if rank[sa[n - 1]] == n - 1 || k >= n {
+            return sa;
+        }
+        k *= 2;
+    }
+}
+
+/// Statistics of repeated tuples calculated from the longest common prefixes of suffixes.
+struct Tuples {
+    /// `max_count[w]` - number of occurrences of the most common `w`-tuple.
+    max_count: Vec<usize>,
+    /// `pairs[w]` - number of pairs of equal `w`-tuples.
+    pairs: Vec<f64>,
+}
+
+impl Tuples {
+    fn new(s: &[u8]) -> Self {
+        let n = s.len();
+        let sa = suffix_array(s);
+
+        // Kasai's algorithm, lcp[i] is the common prefix of sa[i] and sa[i + 1].
+        let mut inv = vec![0_usize; n];
+        sa.iter().enumerate().for_each(|(i, v)| inv[*v] = i);
+        let mut lcp = vec![0_usize; n.saturating_sub(1)];
+        let mut h = 0;
+        for i in 0..n {
+            if inv[i] + 1 < n {
+                let j = sa[inv[i] + 1];
+                while i + h < n && j + h < n && s[i + h] == s[j + h] {
+                    h += 1;
+                }
+                lcp[inv[i]] = h;
+                h = h.saturating_sub(1);
+            } else {
+                h = 0;
+            }
+        }
+
+        let max_lcp = lcp.iter().copied().max().unwrap_or(0);
+
+        // Ranges where lcp[i] is the minimum: strictly greater values to the left,
+        // greater or equal to the right, so each range of suffixes is counted once.
+        let m = lcp.len();
+        let mut left = vec![0_usize; m];
+        let mut right = vec![0_usize; m];
+        let mut stack: Vec<usize> = vec![];
+        for i in 0..m {
+            while stack.last().is_some_and(|j| lcp[*j] > lcp[i]) {
+                stack.pop();
+            }
+            left[i] = stack.last().map_or(i, |j| i - j - 1);
+            stack.push(i);
+        }
+        stack.clear();
+        for i in (0..m).rev() {
+            while stack.last().is_some_and(|j| lcp[*j] >= lcp[i]) {
+                stack.pop();
+            }
+            right[i] = stack.last().map_or(m - 1 - i, |j| j - i - 1);
+            stack.push(i);
+        }
+
+        let mut max_count = vec![1_usize; max_lcp + 2];
+        let mut pairs = vec![0_f64; max_lcp + 2];
+        for i in 0..m {
+            max_count[lcp[i]] = max_count[lcp[i]].max(left[i] + right[i] + 2);
+            pairs[lcp[i]] += ((left[i] + 1) * (right[i] + 1)) as f64;
+        }
+        for w in (1..=max_lcp).rev() {
+            max_count[w] = max_count[w].max(max_count[w + 1]);
+            pairs[w] += pairs[w + 1];
+        }
+
+        Self { max_count, pairs }
+    }
+
+    /// The longest tuple which occurs at least 35 times.
+    fn t(&self) -> usize {
+        (1..self.max_count.len())
+            .take_while(|w| self.max_count[*w] >= 35)
+            .last()
+            .unwrap_or(0)
+    }
+
+    /// t-Tuple estimate (6.3.5).
+    fn t_tuple(&self, n: usize) -> Option<f64> {
+        let p = (1..=self.t())
+            .map(|w| (self.max_count[w] as f64 / (n - w + 1) as f64).powf(1_f64 / w as f64))
+            .fold(None, |acc: Option<f64>, v| {
+                Some(acc.map_or(v, |a| a.max(v)))
+            })?;
+        Some(min_entropy(upper_bound(p, n)))
+    }
+
+    /// Longest repeated substring estimate (6.3.6).
+    fn lrs(&self, n: usize) -> Option<f64> {
+        let v = self.pairs.len() - 2;
+        let p = (self.t() + 1..=v)
+            .map(|w| {
+                let total = (n - w + 1) as f64;
+                (self.pairs[w] / (total * (total - 1_f64) / 2_f64)).powf(1_f64 / w as f64)
+            })
+            .fold(None, |acc: Option<f64>, v| {
+                Some(acc.map_or(v, |a| a.max(v)))
+            })?;
+        Some(min_entropy(upper_bound(p, n)))
+    }
+}
+
+/// Estimate from the results of a predictor (6.3.7, steps 5 - 9), `k` - number of possible samples.
+fn prediction_estimate(correct: &[bool], k: usize) -> f64 {
+    let n = correct.len() as f64;
+    let c = correct.iter().filter(|v| **v).count() as f64;
+
+    let p_global = if c == 0_f64 {
+        1_f64 - 0.01_f64.powf(1_f64 / n)
+    } else {
+        let p = c / n;
+        (p + Z_ALPHA * (p * (1_f64 - p) / (n - 1_f64)).sqrt()).min(1_f64)
+    };
+
+    let mut longest = 0;
+    let mut run = 0;
+    correct.iter().for_each(|v| {
+        run = if *v { run + 1 } else { 0 };
+        longest = longest.max(run);
+    });
+    let r = (longest + 1) as f64;
+
+    // Probability of no run of r correct predictions in n predictions.
+    let no_run = |p: f64| {
+        let q = 1_f64 - p;
+        let mut x = 1_f64;
+        for _ in 0..10 {
+            x = 1_f64 + q * p.powf(r) * x.powf(r + 1_f64);
+        }
+        let num = 1_f64 - p * x;
+        let den = (r + 1_f64 - r * x) * q;
+        if num <= 0_f64 || den <= 0_f64 {
+            return 0_f64;
+        }
+        (num.ln() - den.ln() - (n + 1_f64) * x.ln()).exp()
+    };
+
+    let (mut lo, mut hi) = (0_f64, 1_f64);
+    for _ in 0..64 {
+        let mid = (lo + hi) / 2_f64;
+        if no_run(mid) > 0.99 {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    min_entropy(p_global.max(lo).max(1_f64 / k as f64))
+}
+
+/// Chooses the prediction of the subpredictor with the best score and updates the scores.
+struct Scoreboard {
+    scores: Vec<usize>,
+    winner: usize,
+}
+
+impl Scoreboard {
+    fn new(n: usize) -> Self {
+        Self {
+            scores: vec![0; n],
+            winner: 0,
+        }
+    }
+
+    /// Returns whether the prediction of the winner was correct.
+    fn update(&mut self, predictions: &[Option<u8>], value: u8) -> bool {
+        let correct = predictions[self.winner] == Some(value);
+        for (j, prediction) in predictions.iter().enumerate() {
+            if *prediction == Some(value) {
+                self.scores[j] += 1;
+                if self.scores[j] >= self.scores[self.winner] {
+                    self.winner = j;
+                }
+            }
+        }
+        correct
+    }
+}
+
+/// Most common value in a sliding window, ties are broken by the most recent value.
+struct Window {
+    len: usize,
+    /// Number of possible samples.
+    k: usize,
+    counts: [usize; 256],
+    last: [usize; 256],
+    mode: u8,
+}
+
+impl Window {
+    fn new(len: usize, k: usize) -> Self {
+        Self {
+            len,
+            k,
+            counts: [0; 256],
+            last: [0; 256],
+            mode: 0,
+        }
+    }
+
+    /// Adds `s[i]` to the window of samples ending at `i`.
+    fn push(&mut self, s: &[u8], i: usize) {
+        let v = s[i];
+        self.counts[v as usize] += 1;
+        self.last[v as usize] = i;
+        if self.counts[v as usize] >= self.counts[self.mode as usize] {
+            self.mode = v;
+        }
+
+        if i >= self.len {
+            let old = s[i - self.len];
+            self.counts[old as usize] -= 1;
+            if old == self.mode {
+                self.mode = (0..self.k)
+                    .max_by_key(|v| (self.counts[*v], self.last[*v]))
+                    .unwrap_or(0) as u8;
+            }
+        }
+    }
+}
+
+/// MultiMCW prediction estimate (6.3.7).
+fn multi_mcw(s: &[u8], k: usize) -> Option<f64> {
+    const W: [usize; 4] = [63, 255, 1023, 4095];
+    if s.len() <= W[0] + 1 {
+        return None;
+    }
+
+    let mut windows = W.map(|len| Window::new(len, k));
+    let mut scoreboard = Scoreboard::new(W.len());
+    let mut predictions = [None; 4];
+    let mut correct = vec![];
+
+    for i in 0..s.len() {
+        if i >= W[0] {
+            for (j, window) in windows.iter().enumerate() {
+                predictions[j] = (i >= W[j]).then_some(window.mode);
+            }
+            correct.push(scoreboard.update(&predictions, s[i]));
+        }
+        windows.iter_mut().for_each(|w| w.push(s, i));
+    }
+
+    Some(prediction_estimate(&correct, k))
+}
+
+/// Lag prediction estimate (6.3.8).
+fn lag(s: &[u8], k: usize) -> Option<f64> {
+    const D: usize = 128;
+    if s.len() < 3 {
+        return None;
+    }
+
+    let mut scoreboard = Scoreboard::new(D);
+    let mut predictions = [None; D];
+    let correct: Vec<bool> = (1..s.len())
+        .map(|i| {
+            for (d, prediction) in predictions.iter_mut().enumerate() {
+                *prediction = s.get(i.wrapping_sub(d + 1)).copied().filter(|_| i > d);
+            }
+            scoreboard.update(&predictions, s[i])
+        })
+        .collect();
+
+    Some(prediction_estimate(&correct, k))
+}
+
+/// Counts of samples following a context, the most frequent one is predicted.
+#[derive(Default)]
+struct Followers {
+    counts: Vec<(u8, usize)>,
+    /// The most frequent sample, ties are broken by the largest sample.
+    best: Option<(u8, usize)>,
+}
+
+impl Followers {
+    fn add(&mut self, v: u8) {
+        let count = match self.counts.iter_mut().find(|(x, _)| *x == v) {
+            Some((_, count)) => {
+                *count += 1;
+                *count
+            }
+            None => {
+                self.counts.push((v, 1));
+                1
+            }
+        };
+
+        if self.best.is_none_or(|best| (count, v) > (best.1, best.0)) {
+            self.best = Some((v, count));
+        }
+    }
+
+    fn predict(&self) -> Option<(u8, usize)> {
+        self.best
+    }
+}
+
+/// Contexts of 1 to 16 samples preceding `end`, the last sample is in the lowest byte.
+fn contexts(s: &[u8], end: usize) -> [u128; 16] {
+    let mut ctx = [0_u128; 16];
+    let mut acc = 0_u128;
+    for (d, v) in s[end.saturating_sub(16)..end].iter().rev().enumerate() {
+        acc |= (*v as u128) << (8 * d);
+        ctx[d] = acc;
+    }
+    ctx
+}
+
+/// MultiMMC prediction estimate (6.3.9).
+fn multi_mmc(s: &[u8], k: usize) -> Option<f64> {
+    const D: usize = 16;
+    const MAX_ENTRIES: usize = 100_000;
+    if s.len() < 4 {
+        return None;
+    }
+
+    let mut models: Vec<HashMap<u128, Followers>> = (0..D).map(|_| HashMap::new()).collect();
+    let mut entries = [0_usize; D];
+    let mut scoreboard = Scoreboard::new(D);
+    let mut predictions = [None; D];
+    let mut correct = vec![];
+
+    for i in 2..s.len() {
+        let prev = contexts(s, i - 1);
+        let ctx = contexts(s, i);
+        for d in 1..=D {
+            if d < i {
+                let model = &mut models[d - 1];
+                if let Some(followers) = model.get_mut(&prev[d - 1]) {
+                    followers.add(s[i - 1]);
+                } else if entries[d - 1] < MAX_ENTRIES {
+                    model.entry(prev[d - 1]).or_default().add(s[i - 1]);
+                    entries[d - 1] += 1;
+                }
+            }
+        }
+
+        for d in 1..=D {
+            predictions[d - 1] = (d <= i)
+                .then(|| models[d - 1].get(&ctx[d - 1]))
+                .flatten()
+                .and_then(Followers::predict)
+                .map(|(v, _)| v);
+        }
+
+        correct.push(scoreboard.update(&predictions, s[i]));
+    }
+
+    Some(prediction_estimate(&correct, k))
+}
+
+/// LZ78Y prediction estimate (6.3.10).
+fn lz78y(s: &[u8], k: usize) -> Option<f64> {
+    const B: usize = 16;
+    const MAX_DICTIONARY: usize = 65_536;
+    if s.len() < B + 3 {
+        return None;
+    }
+
+    let mut dict: Vec<HashMap<u128, Followers>> = (0..B).map(|_| HashMap::new()).collect();
+    let mut size = 0;
+    let mut correct = vec![];
+
+    for i in B + 1..s.len() {
+        let prev = contexts(s, i - 1);
+        let ctx = contexts(s, i);
+        for j in (1..=B).rev() {
+            if let Some(followers) = dict[j - 1].get_mut(&prev[j - 1]) {
+                followers.add(s[i - 1]);
+            } else if size < MAX_DICTIONARY {
+                dict[j - 1].entry(prev[j - 1]).or_default().add(s[i - 1]);
+                size += 1;
+            }
+        }
+
+        let mut prediction = None;
+        let mut max_count = 0;
+        for j in (1..=B).rev() {
+            if let Some((v, count)) = dict[j - 1].get(&ctx[j - 1]).and_then(Followers::predict) {
+                if count > max_count {
+                    prediction = Some(v);
+                    max_count = count;
+                }
+            }
+        }
+
+        correct.push(prediction == Some(s[i]));
+    }
+
+    Some(prediction_estimate(&correct, k))
+}
+
+/// Min-entropy per sample of `s` by `estimator`, `None` if there are too few samples.
+fn estimate(estimator: Estimator, s: &[u8], k: usize, tuples: &mut Option<Tuples>) -> Option<f64> {
+    match estimator {
+        Estimator::MostCommonValue => Some(most_common_value(s)),
+        Estimator::Collision => Some(collision(s)),
+        Estimator::Markov => Some(markov(s)),
+        Estimator::Compression => compression(s),
+        Estimator::TTuple => tuples
+            .get_or_insert_with(|| Tuples::new(s))
+            .t_tuple(s.len()),
+        Estimator::Lrs => tuples.get_or_insert_with(|| Tuples::new(s)).lrs(s.len()),
+        Estimator::MultiMcw => multi_mcw(s, k),
+        Estimator::Lag => lag(s, k),
+        Estimator::MultiMmc => multi_mmc(s, k),
+        Estimator::Lz78y => lz78y(s, k),
+    }
+}
+
+/// Estimators used on the samples and on the bitstring of the samples with `width` bits.
+fn plan(width: usize, iid: bool) -> Vec<(Estimator, bool)> {
+    let estimators = ESTIMATORS
+        .into_iter()
+        .filter(|e| !iid || *e == Estimator::MostCommonValue);
+
+    let samples = estimators
+        .clone()
+        .filter(|e| width == 1 || !e.binary_only())
+        .map(|e| (e, false));
+    let bitstring = estimators.filter(|_| width > 1).map(|e| (e, true));
+
+    samples.chain(bitstring).collect()
+}
+
+/// The SP 800-90B estimators run on a block of samples, the last result is H_min.
+struct EntropyTest {
+    params: [ParamSpec; 2],
+}
+
+fn width(params: &[TestParam]) -> usize {
+    params[0].int()
+}
+
+fn iid(params: &[TestParam]) -> bool {
+    params[1].value == ParamValue::Bool(true)
+}
+
+impl RandomnessTest for EntropyTest {
+    fn name(&self) -> &'static str {
+        "Entropy"
+    }
+
+    fn params(&self) -> &[ParamSpec] {
+        &self.params
+    }
+
+    fn min_length(&self, _params: &[TestParam]) -> usize {
+        MIN_SAMPLES * u8::BITS as usize
+    }
+
+    fn run(
+        &self,
+        data: &BitsData,
+        params: &[TestParam],
+    ) -> Result<Vec<TestResultT>, Option<String>> {
+        let width = width(params);
+        let mask = ((1_u16 << width) - 1) as u8;
+        let samples: Vec<u8> = data[..]
+            .chunks_exact(u8::BITS as usize)
+            .map(|v| v.iter().fold(0_u8, |acc, bit| (acc << 1) | *bit as u8) & mask)
+            .collect();
+
+        let bitstring: Vec<u8> = samples
+            .iter()
+            .flat_map(|v| (0..width).rev().map(move |i| (v >> i) & 1))
+            .take(MAX_BITSTRING)
+            .collect();
+
+        let mut sample_tuples = None;
+        let mut bit_tuples = None;
+        let mut h_samples = f64::INFINITY;
+        let mut h_bits = f64::INFINITY;
+
+        let mut results = vec![];
+        for (estimator, on_bits) in plan(width, iid(params)) {
+            let h = if on_bits {
+                estimate(estimator, &bitstring, 2, &mut bit_tuples)
+            } else {
+                estimate(estimator, &samples, 1 << width, &mut sample_tuples)
+            }
+            .ok_or(Some(format!(
+                "too few samples for the {} estimate",
+                estimator.name()
+            )))?;
+
+            if on_bits {
+                h_bits = h_bits.min(h);
+            } else {
+                h_samples = h_samples.min(h);
+            }
+            results.push((true, h));
+        }
+
+        results.push((true, h_samples.min(width as f64 * h_bits)));
+        Ok(results)
+    }
+
+    fn labels(&self, params: &[TestParam]) -> Vec<String> {
+        let width = width(params);
+        plan(width, iid(params))
+            .into_iter()
+            .map(|(e, on_bits)| match (width, on_bits) {
+                (1, _) => e.name().to_string(),
+                (_, false) => format!("{} samples", e.name()),
+                (_, true) => format!("{} bitstring", e.name()),
+            })
+            .chain(std::iter::once("H_min".to_string()))
+            .collect()
+    }
+}
+
+/// The SP 800-90B entropy assessment.
+pub fn entropy_tests() -> Vec<NistWrapper> {
+    vec![NistWrapper::new(Arc::new(EntropyTest {
+        params: [
+            ParamSpec::int(
+                "Sample bits",
+                "Bits of a sample stored in each byte",
+                1,
+                8,
+                8,
+            ),
+            ParamSpec {
+                name: "IID",
+                description: "Samples are independent and identically distributed, \
+                              only the most common value estimate is used",
+                kind: ParamKind::Bool,
+                default: ParamValue::Bool(false),
+            },
+        ],
+    }))]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn most_common_value_example() {
+        // p = 8/20, p_u = 0.4 + 2.576 * sqrt(0.4 * 0.6 / 19).
+        let s = [0, 1, 1, 2, 0, 1, 2, 2, 0, 1, 0, 1, 1, 0, 2, 2, 1, 0, 2, 1];
+        assert_close(most_common_value(&s), 0.536341123506687);
+    }
+
+    #[test]
+    fn collision_bounds() {
+        assert_close(collision(&[0; 1000]), 0_f64);
+        let alternating: Vec<u8> = (0..1000_usize).map(|i| (i % 2) as u8).collect();
+        assert_close(collision(&alternating), 1_f64);
+    }
+
+    #[test]
+    fn markov_bounds() {
+        assert_close(markov(&[0; 1000]), 0_f64);
+        // The most likely sequence 0101... has the probability 1/2.
+        let alternating: Vec<u8> = (0..1000_usize).map(|i| (i % 2) as u8).collect();
+        assert_close(markov(&alternating), 1_f64 / 128_f64);
+    }
+
+    #[test]
+    fn prediction_bounds() {
+        assert_close(prediction_estimate(&[true; 1000], 2), 0_f64);
+        // Without correct predictions the estimate is limited by guessing one of k samples.
+        assert_close(prediction_estimate(&[false; 1000], 2), 1_f64);
+        assert_close(prediction_estimate(&[false; 1000], 16), 4_f64);
+    }
+
+    #[test]
+    fn predicts_periodic_samples() {
+        let periodic: Vec<u8> = (0..5000_usize).map(|i| (i % 3) as u8).collect();
+        assert!(lag(&periodic, 4).unwrap() < 0.01);
+        assert!(multi_mmc(&periodic, 4).unwrap() < 0.01);
+        assert!(multi_mcw(&[5; 5000], 8).unwrap() < 0.01);
+        assert!(lag(&[0, 1], 2).is_none());
+    }
+}
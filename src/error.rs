@@ -0,0 +1,65 @@
+use std::fmt;
+
+use serde::Serialize;
+
+/// Errors reported by a run. `Open`, `Read` and `Disconnected` abort the run,
+/// the others are recorded and the run goes on with the next test.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind")]
+pub enum TestError {
+    /// The input can't be opened.
+    Open { path: String, message: String },
+    /// Reading of the block `block` failed.
+    Read { block: usize, message: String },
+    /// The test returned an error on the block `block`.
+    Test {
+        test: &'static str,
+        block: usize,
+        message: String,
+    },
+    /// The test is not applicable to the block `block`,
+    /// e.g. RandomExcursions on a sequence with too few cycles.
+    NotApplicable { test: &'static str, block: usize },
+    /// The worker thread exited without sending a result.
+    Disconnected,
+}
+
+impl TestError {
+    pub fn test(&self) -> Option<&'static str> {
+        match self {
+            TestError::Test { test, .. } | TestError::NotApplicable { test, .. } => Some(test),
+            _ => None,
+        }
+    }
+
+    pub fn block(&self) -> Option<usize> {
+        match self {
+            TestError::Read { block, .. }
+            | TestError::Test { block, .. }
+            | TestError::NotApplicable { block, .. } => Some(*block),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TestError::Open { path, message } => write!(f, "Can't open file {}: {}", path, message),
+            TestError::Read { block, message } => {
+                write!(f, "Block {}: error read sequence: {}", block, message)
+            }
+            TestError::Test {
+                test,
+                block,
+                message,
+            } => write!(f, "Block {}: {}: {}", block, test, message),
+            TestError::NotApplicable { test, block } => {
+                write!(f, "Block {}: {}: test is not applicable", block, test)
+            }
+            TestError::Disconnected => write!(f, "Test thread stopped"),
+        }
+    }
+}
+
+impl std::error::Error for TestError {}
@@ -0,0 +1,167 @@
+use std::fmt::Write as FmtWrite;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::report::Report;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    /// Format by the extension of `path`, the text report for unknown extensions.
+    pub fn from_path(path: &Path) -> Self {
+        let ext = path.extension().and_then(|v| v.to_str()).unwrap_or("");
+        if ext.eq_ignore_ascii_case("json") {
+            ExportFormat::Json
+        } else if ext.eq_ignore_ascii_case("csv") {
+            ExportFormat::Csv
+        } else {
+            ExportFormat::Text
+        }
+    }
+}
+
+/// Report with the run settings, one object per result and errors.
+pub fn to_json(report: &Report) -> String {
+    serde_json::to_string_pretty(report).expect("Report is always serializable")
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// One line per result, or per estimator for SP 800-90B reports.
+pub fn to_csv(report: &Report) -> String {
+    if !report.estimates.is_empty() {
+        let mut out = String::from("estimator,min_entropy\n");
+        report.estimates.iter().for_each(|v| {
+            writeln!(out, "{},{}", csv_field(&v.name), v.min_entropy).unwrap();
+        });
+        return out;
+    }
+
+    let mut out = String::from("test,label");
+    (1..=10).for_each(|i| write!(out, ",C{}", i).unwrap());
+    out +=
+        ",p_value,proportion,min_proportion,max_proportion,samples,errors,not_applicable,passed,evaluated,failed_blocks\n";
+
+    report.rows.iter().for_each(|row| {
+        write!(out, "{},{}", csv_field(row.name), csv_field(&row.label)).unwrap();
+        row.p_distr
+            .iter()
+            .for_each(|p| write!(out, ",{}", p).unwrap());
+        let failed_blocks: Vec<String> = row
+            .failed_blocks
+            .iter()
+            .map(|v| v.block.to_string())
+            .collect();
+        writeln!(
+            out,
+            ",{},{},{},{},{},{},{},{},{},{}",
+            row.uniformity,
+            row.proportion,
+            row.proportion_bounds.0,
+            row.proportion_bounds.1,
+            row.samples,
+            row.errors,
+            row.not_applicable,
+            row.passed,
+            row.evaluated,
+            failed_blocks.join(" ")
+        )
+        .unwrap();
+    });
+
+    out
+}
+
+/// Writes the report to `path`. Fails with [`io::ErrorKind::AlreadyExists`]
+/// if the file exists and `overwrite` is not set.
+pub fn export(
+    report: &Report,
+    path: &Path,
+    format: ExportFormat,
+    overwrite: bool,
+) -> io::Result<()> {
+    let data = match format {
+        ExportFormat::Text => report.text.clone(),
+        ExportFormat::Json => to_json(report),
+        ExportFormat::Csv => to_csv(report),
+    };
+
+    let mut file = if overwrite {
+        File::create(path)?
+    } else {
+        OpenOptions::new().write(true).create_new(true).open(path)?
+    };
+
+    file.write_all(data.as_bytes())
+}
+
+/// UTC time `secs` since the Unix epoch as `YYYYMMDD-HHMMSS`.
+fn timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let time = secs % 86_400;
+
+    // Civil date from days since 1970-01-01 (H. Hinnant, "chrono-Compatible Low-Level Date Algorithms").
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
+
+/// Path of the text report for the input `input`.
+/// `output` is a directory or a file; if it's empty the report is written next to the input.
+/// With `started_at` set, the time of the run is added to the file name.
+pub fn report_path(input: &str, output: &str, started_at: Option<u64>) -> PathBuf {
+    let input = Path::new(input);
+    let file_name = format!(
+        "{}.txt",
+        input.file_name().unwrap_or_default().to_string_lossy()
+    );
+
+    let output_is_dir =
+        output.ends_with(['/', std::path::MAIN_SEPARATOR]) || Path::new(output).is_dir();
+    let mut path = if output.is_empty() {
+        input.with_file_name(file_name)
+    } else if output_is_dir {
+        Path::new(output).join(file_name)
+    } else {
+        PathBuf::from(output)
+    };
+
+    if let Some(secs) = started_at {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let name = match path.extension() {
+            Some(ext) => format!("{}_{}.{}", stem, timestamp(secs), ext.to_string_lossy()),
+            None => format!("{}_{}", stem, timestamp(secs)),
+        };
+        path.set_file_name(name);
+    }
+
+    path
+}
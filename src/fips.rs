@@ -0,0 +1,203 @@
+//! FIPS 140-2 statistical random number generator tests (section 4.9.1).
+
+use nistrs::{BitsData, TestResultT};
+
+use crate::tests::{NistWrapper, RandomnessTest, TestParam};
+
+use std::sync::Arc;
+
+/// Length of a block tested by each test.
+pub const BLOCK_BITS: usize = 20_000;
+
+/// Allowed numbers of runs of length 1, 2, 3, 4, 5 and 6+ of each bit.
+const RUNS_INTERVALS: [(usize, usize); 6] = [
+    (2315, 2685),
+    (1114, 1386),
+    (527, 723),
+    (240, 384),
+    (103, 209),
+    (103, 209),
+];
+
+/// A run of 26 or more ones or zeros fails the long run test.
+const LONG_RUN: usize = 26;
+
+/// Lengths of runs of identical bits with the value of the bit.
+pub fn runs(bits: &[bool]) -> Vec<(bool, usize)> {
+    let mut runs: Vec<(bool, usize)> = vec![];
+    bits.iter().for_each(|bit| match runs.last_mut() {
+        Some((last, len)) if last == bit => *len += 1,
+        _ => runs.push((*bit, 1)),
+    });
+    runs
+}
+
+/// Passes if 9725 < number of ones < 10275, returns the number of ones.
+pub fn monobit_test(data: &BitsData) -> TestResultT {
+    let x = data.ones();
+    (9725 < x && x < 10275, x as f64)
+}
+
+/// Passes if 2.16 < X < 46.17 for the frequencies of 4-bit segments, returns X.
+pub fn poker_test(data: &BitsData) -> TestResultT {
+    let mut f = [0_usize; 16];
+    data[..BLOCK_BITS]
+        .chunks(4)
+        .for_each(|v| f[v.iter().fold(0, |acc, bit| (acc << 1) | *bit as usize)] += 1);
+
+    let x = 16_f64 / 5000_f64 * f.iter().map(|v| (v * v) as f64).sum::<f64>() - 5000_f64;
+    (2.16 < x && x < 46.17, x)
+}
+
+/// Passes if numbers of runs of both bits are in [`RUNS_INTERVALS`],
+/// returns the number of counts out of the intervals.
+pub fn runs_test(data: &BitsData) -> TestResultT {
+    let mut counts = [[0_usize; 6]; 2];
+    runs(&data[..])
+        .into_iter()
+        .for_each(|(bit, len)| counts[bit as usize][len.min(6) - 1] += 1);
+
+    let failed = counts
+        .iter()
+        .flat_map(|c| c.iter().zip(RUNS_INTERVALS))
+        .filter(|(count, (min, max))| !(min..=max).contains(count))
+        .count();
+
+    (failed == 0, failed as f64)
+}
+
+/// Passes if there are no runs of [`LONG_RUN`] or more bits, returns the longest run.
+pub fn long_run_test(data: &BitsData) -> TestResultT {
+    let longest = runs(&data[..])
+        .into_iter()
+        .map(|(_, len)| len)
+        .max()
+        .unwrap_or(0);
+    (longest < LONG_RUN, longest as f64)
+}
+
+/// A test of one block of [`BLOCK_BITS`].
+struct FipsTest {
+    name: &'static str,
+    test: fn(&BitsData) -> TestResultT,
+}
+
+impl RandomnessTest for FipsTest {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn min_length(&self, _params: &[TestParam]) -> usize {
+        BLOCK_BITS
+    }
+
+    fn max_length(&self, _params: &[TestParam]) -> usize {
+        BLOCK_BITS
+    }
+
+    fn run(
+        &self,
+        data: &BitsData,
+        _params: &[TestParam],
+    ) -> Result<Vec<TestResultT>, Option<String>> {
+        if data.len() != BLOCK_BITS {
+            return Err(Some(format!(
+                "needs a block of {} bits, got {}",
+                BLOCK_BITS,
+                data.len()
+            )));
+        }
+
+        Ok(vec![(self.test)(data)])
+    }
+}
+
+/// The FIPS 140-2 tests.
+pub fn fips_tests() -> Vec<NistWrapper> {
+    [
+        FipsTest {
+            name: "Monobit",
+            test: monobit_test,
+        },
+        FipsTest {
+            name: "Poker",
+            test: poker_test,
+        },
+        FipsTest {
+            name: "Runs",
+            test: runs_test,
+        },
+        FipsTest {
+            name: "LongRun",
+            test: long_run_test,
+        },
+    ]
+    .into_iter()
+    .map(|test| NistWrapper::new(Arc::new(test)))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Block of 4-bit segments counting 0, 1, ..., 15, 0, 1, ...
+    fn counter() -> BitsData {
+        BitsData::from_binary(
+            (0..BLOCK_BITS / 8)
+                .map(|i| (((2 * i % 16) << 4) | ((2 * i + 1) % 16)) as u8)
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn splits_runs() {
+        assert_eq!(
+            runs(&[true, true, false, true, true, true]),
+            [(true, 2), (false, 1), (true, 3)]
+        );
+        assert!(runs(&[]).is_empty());
+    }
+
+    #[test]
+    fn fails_zeros() {
+        let zeros = BitsData::from_binary(vec![0; BLOCK_BITS / 8]);
+        assert_eq!(monobit_test(&zeros), (false, 0_f64));
+        // X = 16 / 5000 * 5000^2 - 5000.
+        assert_eq!(poker_test(&zeros), (false, 75_000_f64));
+        // A single run of zeros, every count is out of its interval.
+        assert_eq!(runs_test(&zeros), (false, 12_f64));
+        assert_eq!(long_run_test(&zeros), (false, BLOCK_BITS as f64));
+    }
+
+    #[test]
+    fn counter_is_too_uniform() {
+        let data = counter();
+        // 0..7 occur 313 times, 8..15 - 312 times.
+        assert_eq!(monobit_test(&data), (true, 9996_f64));
+        let (passed, x) = poker_test(&data);
+        assert!(!passed);
+        assert!((x - 0.0128).abs() < 1e-9, "{}", x);
+        // 7 zeros in 0xf0, 0x01.
+        assert_eq!(long_run_test(&data), (true, 7_f64));
+    }
+
+    #[test]
+    fn fails_alternating_bits() {
+        let data = BitsData::from_binary(vec![0x55; BLOCK_BITS / 8]);
+        assert_eq!(monobit_test(&data), (true, 10_000_f64));
+        // 10000 runs of length 1 and none of 2..6+ for both bits.
+        assert_eq!(runs_test(&data), (false, 12_f64));
+        assert_eq!(long_run_test(&data), (true, 1_f64));
+    }
+
+    #[test]
+    fn finds_long_run() {
+        let mut bytes = vec![0x55; BLOCK_BITS / 8];
+        bytes[100..104].copy_from_slice(&[0x7f, 0xff, 0xff, 0xfe]);
+        assert_eq!(
+            long_run_test(&BitsData::from_binary(bytes)),
+            (false, 30_f64)
+        );
+    }
+}
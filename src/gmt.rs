@@ -0,0 +1,438 @@
+//! GM/T 0005-2021 randomness tests. The tests shared with SP 800-22 run on `nistrs`,
+//! poker, runs distribution, binary derivation and autocorrelation are implemented here.
+
+use std::f64::consts::SQRT_2;
+use std::sync::Arc;
+
+use nistrs::prelude::*;
+use nistrs::{BitsData, TestResultT, TEST_THRESHOLD};
+use statrs::function::erf::erfc;
+use statrs::function::gamma::gamma_ur;
+
+use crate::fips;
+use crate::tests::{pow2, FnTest, NistWrapper, ParamSpec, TestParam};
+use crate::universal;
+
+/// Length of a sequence the default parameters are chosen for.
+pub const BLOCK_BITS: usize = 1_000_000;
+
+fn result(p_value: f64) -> TestResultT {
+    (p_value >= TEST_THRESHOLD, p_value)
+}
+
+/// Upper incomplete gamma function, 1 for a zero statistic.
+fn igamc(a: f64, x: f64) -> f64 {
+    if x > 0_f64 {
+        gamma_ur(a, x)
+    } else {
+        1_f64
+    }
+}
+
+/// psi^2 statistic of overlapping `m`-bit patterns of the cyclic sequence.
+fn psi2(bits: &[bool], m: usize) -> f64 {
+    if m == 0 {
+        return 0_f64;
+    }
+
+    let n = bits.len();
+    let mask = (1_usize << m) - 1;
+    let mut counts = vec![0_usize; 1 << m];
+    let mut pattern = bits[n - m + 1..]
+        .iter()
+        .fold(0, |acc, bit| (acc << 1) | *bit as usize);
+    bits.iter().for_each(|bit| {
+        pattern = ((pattern << 1) | *bit as usize) & mask;
+        counts[pattern] += 1;
+    });
+
+    (1_usize << m) as f64 / n as f64 * counts.iter().map(|c| (c * c) as f64).sum::<f64>() - n as f64
+}
+
+/// Serial test. `nistrs` takes psi^2 of 1-bit patterns as zero, so it fails for m = 3.
+pub fn serial_test(data: &BitsData, m: usize) -> [TestResultT; 2] {
+    let bits = &data[..];
+    let psi = [psi2(bits, m), psi2(bits, m - 1), psi2(bits, m - 2)];
+
+    let del1 = psi[0] - psi[1];
+    let del2 = psi[0] - 2_f64 * psi[1] + psi[2];
+    [
+        result(igamc(2_f64.powi(m as i32 - 2), del1 / 2_f64)),
+        result(igamc(2_f64.powi(m as i32 - 3), del2 / 2_f64)),
+    ]
+}
+
+/// Poker test with patterns of `m` bits.
+pub fn poker_test(data: &BitsData, m: usize) -> TestResultT {
+    let mut counts = vec![0_usize; 1 << m];
+    data[..].chunks_exact(m).for_each(|v| {
+        counts[v.iter().fold(0, |acc, bit| (acc << 1) | *bit as usize)] += 1;
+    });
+
+    let n = (data.len() / m) as f64;
+    let k = counts.len() as f64;
+    let v = k / n * counts.iter().map(|c| (c * c) as f64).sum::<f64>() - n;
+    result(igamc((k - 1_f64) / 2_f64, v / 2_f64))
+}
+
+/// Runs distribution test: numbers of runs of each length of both bits.
+pub fn runs_distribution_test(data: &BitsData) -> Result<TestResultT, String> {
+    let n = data.len() as f64;
+
+    // The longest run length with at least 5 expected runs.
+    let k = (1..)
+        .take_while(|i| (n - *i as f64 + 3_f64) / 2_f64.powi(*i + 2) >= 5_f64)
+        .last()
+        .unwrap_or(0) as usize;
+    if k < 2 {
+        return Err(format!("sequence of {} bits is too short", data.len()));
+    }
+
+    let mut counts = vec![[0_usize; 2]; k];
+    fips::runs(&data[..])
+        .into_iter()
+        .for_each(|(bit, len)| counts[len.min(k) - 1][bit as usize] += 1);
+
+    let total = counts.iter().map(|c| c[0] + c[1]).sum::<usize>() as f64;
+    let v = counts
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let e = if i + 1 < k {
+                total / 2_f64.powi(i as i32 + 2)
+            } else {
+                total / 2_f64.powi(k as i32)
+            };
+            c.iter().map(|v| (*v as f64 - e).powi(2) / e).sum::<f64>()
+        })
+        .sum::<f64>();
+
+    Ok(result(igamc(k as f64 - 1_f64, v / 2_f64)))
+}
+
+/// Binary derivation test: the frequency of the sequence XOR-ed with itself shifted by one `k` times.
+pub fn binary_derivation_test(data: &BitsData, k: usize) -> Result<TestResultT, String> {
+    if data.len() <= k {
+        return Err(format!(
+            "sequence of {} bits is too short for k = {}",
+            data.len(),
+            k
+        ));
+    }
+
+    let mut bits = data[..].to_vec();
+    for _ in 0..k {
+        bits = bits.windows(2).map(|v| v[0] ^ v[1]).collect();
+    }
+
+    let s = bits
+        .iter()
+        .map(|bit| if *bit { 1 } else { -1 })
+        .sum::<isize>() as f64;
+    let v = s / (bits.len() as f64).sqrt();
+    Ok(result(erfc(v.abs() / SQRT_2)))
+}
+
+/// Autocorrelation test with the shift `d`.
+pub fn autocorrelation_test(data: &BitsData, d: usize) -> Result<TestResultT, String> {
+    let bits = &data[..];
+    if bits.len() <= d {
+        return Err(format!(
+            "sequence of {} bits is too short for d = {}",
+            bits.len(),
+            d
+        ));
+    }
+
+    let n = (bits.len() - d) as f64;
+    let a = bits.iter().zip(&bits[d..]).filter(|(x, y)| x != y).count() as f64;
+
+    let v = 2_f64 * (a - n / 2_f64) / n.sqrt();
+    Ok(result(erfc(v.abs() / SQRT_2)))
+}
+
+/// Labels of a test run with each value of its parameters.
+fn values_labels(name: &str, params: &[TestParam]) -> Vec<String> {
+    params
+        .iter()
+        .map(|p| format!("{}={}", name, p.int()))
+        .collect()
+}
+
+fn no_labels(_: &[TestParam]) -> Vec<String> {
+    vec![]
+}
+
+/// The GM/T 0005-2021 tests with the default parameters for sequences of 10^6 bits.
+pub fn gmt_tests() -> Vec<NistWrapper> {
+    [
+        FnTest {
+            name: "Frequency",
+            min_length: |_| 100,
+            params: vec![],
+            run: |data, _| Ok(vec![frequency_test(data)]),
+            labels: no_labels,
+        },
+        FnTest {
+            name: "BlockFrequency",
+            min_length: |params| params[0].int(),
+            params: vec![ParamSpec::int(
+                "m",
+                "Length of a block",
+                10,
+                1_000_000,
+                10_000,
+            )],
+            run: |data, params| match block_frequency_test(data, params[0].int()) {
+                Ok(v) => Ok(vec![v]),
+                Err(s) => Err(Some(s)),
+            },
+            labels: no_labels,
+        },
+        FnTest {
+            name: "Poker",
+            min_length: |params| {
+                params
+                    .iter()
+                    .map(|p| 5 * p.int() * pow2(p.int()))
+                    .max()
+                    .unwrap_or(0)
+            },
+            params: vec![
+                ParamSpec::int("m1", "Length of a pattern", 2, 16, 4),
+                ParamSpec::int("m2", "Length of a pattern", 2, 16, 8),
+            ],
+            run: |data, params| Ok(params.iter().map(|p| poker_test(data, p.int())).collect()),
+            labels: |params| values_labels("m", params),
+        },
+        FnTest {
+            name: "Serial",
+            min_length: |params| params.iter().map(|p| pow2(p.int() + 3)).max().unwrap_or(0),
+            params: vec![
+                ParamSpec::int("m1", "Length of a pattern", 2, 24, 3),
+                ParamSpec::int("m2", "Length of a pattern", 2, 24, 5),
+            ],
+            run: |data, params| {
+                Ok(params
+                    .iter()
+                    .flat_map(|p| serial_test(data, p.int()))
+                    .collect())
+            },
+            labels: |params| {
+                values_labels("m", params)
+                    .into_iter()
+                    .flat_map(|m| [format!("{} P1", m), format!("{} P2", m)])
+                    .collect()
+            },
+        },
+        FnTest {
+            name: "Runs",
+            min_length: |_| 100,
+            params: vec![],
+            run: |data, _| Ok(vec![runs_test(data)]),
+            labels: no_labels,
+        },
+        FnTest {
+            name: "RunsDistribution",
+            min_length: |_| 100,
+            params: vec![],
+            run: |data, _| match runs_distribution_test(data) {
+                Ok(v) => Ok(vec![v]),
+                Err(s) => Err(Some(s)),
+            },
+            labels: no_labels,
+        },
+        FnTest {
+            name: "LongestRunOfOnes",
+            min_length: |_| 128,
+            params: vec![],
+            run: |data, _| match longest_run_of_ones_test(data) {
+                Ok(v) => Ok(vec![v]),
+                Err(s) => Err(Some(s)),
+            },
+            labels: no_labels,
+        },
+        FnTest {
+            name: "BinaryDerivation",
+            min_length: |_| 100,
+            params: vec![
+                ParamSpec::int("k1", "Number of derivations", 1, 64, 3),
+                ParamSpec::int("k2", "Number of derivations", 1, 64, 7),
+            ],
+            run: |data, params| {
+                params
+                    .iter()
+                    .map(|p| binary_derivation_test(data, p.int()))
+                    .collect::<Result<_, _>>()
+                    .map_err(Some)
+            },
+            labels: |params| values_labels("k", params),
+        },
+        FnTest {
+            name: "Autocorrelation",
+            min_length: |_| 100,
+            params: vec![
+                ParamSpec::int("d1", "Shift", 1, 64, 1),
+                ParamSpec::int("d2", "Shift", 1, 64, 2),
+                ParamSpec::int("d3", "Shift", 1, 64, 8),
+                ParamSpec::int("d4", "Shift", 1, 64, 16),
+            ],
+            run: |data, params| {
+                params
+                    .iter()
+                    .map(|p| autocorrelation_test(data, p.int()))
+                    .collect::<Result<_, _>>()
+                    .map_err(Some)
+            },
+            labels: |params| values_labels("d", params),
+        },
+        FnTest {
+            name: "Rank",
+            min_length: |_| 38_912,
+            params: vec![],
+            run: |data, _| match rank_test(data) {
+                Ok(v) => Ok(vec![v]),
+                Err(s) => Err(Some(s)),
+            },
+            labels: no_labels,
+        },
+        FnTest {
+            name: "CumulativeSums",
+            min_length: |_| 100,
+            params: vec![],
+            run: |data, _| Ok(cumulative_sums_test(data).to_vec()),
+            labels: |_| vec!["Forward".to_string(), "Backward".to_string()],
+        },
+        FnTest {
+            name: "ApproximateEntropy",
+            min_length: |params| params.iter().map(|p| pow2(p.int() + 6)).max().unwrap_or(0),
+            params: vec![
+                ParamSpec::int("m1", "Length of a pattern", 2, 100, 2),
+                ParamSpec::int("m2", "Length of a pattern", 2, 100, 5),
+            ],
+            run: |data, params| {
+                Ok(params
+                    .iter()
+                    .map(|p| approximate_entropy_test(data, p.int()))
+                    .collect())
+            },
+            labels: |params| values_labels("m", params),
+        },
+        FnTest {
+            name: "LinearComplexity",
+            min_length: |params| params.iter().map(|p| 200 * p.int()).max().unwrap_or(0),
+            params: vec![
+                ParamSpec::int("m1", "Length of a block", 500, 5000, 500),
+                ParamSpec::int("m2", "Length of a block", 500, 5000, 1000),
+            ],
+            run: |data, params| {
+                Ok(params
+                    .iter()
+                    .map(|p| linear_complexity_test(data, p.int()))
+                    .collect())
+            },
+            labels: |params| values_labels("m", params),
+        },
+        FnTest {
+            name: "Universal",
+            min_length: |params| universal::min_length(params[0].int(), params[1].int()),
+            params: vec![
+                ParamSpec::int("L", "Length of a block", 6, 16, 7),
+                ParamSpec::int("Q", "Number of initialization blocks", 1, 10_000_000, 1280),
+            ],
+            run: |data, params| match universal::universal_test(
+                data,
+                params[0].int(),
+                params[1].int(),
+            ) {
+                Ok(v) => Ok(vec![v]),
+                Err(s) => Err(Some(s)),
+            },
+            labels: no_labels,
+        },
+        FnTest {
+            name: "FFT",
+            min_length: |_| 1_000,
+            params: vec![],
+            run: |data, _| Ok(vec![fft_test(data)]),
+            labels: no_labels,
+        },
+    ]
+    .into_iter()
+    .map(|test| NistWrapper::new(Arc::new(test)))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 10000 bits of the C library LCG, the upper byte of the state after every step.
+    fn lcg() -> BitsData {
+        let mut x = 1_u64;
+        BitsData::from_binary(
+            (0..1250)
+                .map(|_| {
+                    x = (x * 1_103_515_245 + 12_345) % (1 << 31);
+                    (x >> 16) as u8
+                })
+                .collect(),
+        )
+    }
+
+    /// P-values computed independently in arbitrary precision.
+    fn assert_p_value(result: TestResultT, expected: f64) {
+        assert!(
+            (result.1 - expected).abs() < 1e-9,
+            "{} != {}",
+            result.1,
+            expected
+        );
+        assert_eq!(result.0, expected >= TEST_THRESHOLD);
+    }
+
+    #[test]
+    fn poker() {
+        let data = lcg();
+        assert_p_value(poker_test(&data, 4), 0.574759760945111);
+        assert_p_value(poker_test(&data, 8), 0.364219150953871);
+    }
+
+    #[test]
+    fn serial() {
+        let data = lcg();
+        let [p1, p2] = serial_test(&data, 3);
+        assert_p_value(p1, 0.689959540752717);
+        assert_p_value(p2, 0.477113915520705);
+        let [p1, p2] = serial_test(&data, 5);
+        assert_p_value(p1, 0.988471285369611);
+        assert_p_value(p2, 0.988533282040726);
+    }
+
+    #[test]
+    fn runs_distribution() {
+        assert_p_value(runs_distribution_test(&lcg()).unwrap(), 0.815890453069523);
+    }
+
+    #[test]
+    fn binary_derivation() {
+        let data = lcg();
+        assert_p_value(binary_derivation_test(&data, 3).unwrap(), 0.568619696197781);
+        assert_p_value(binary_derivation_test(&data, 7).unwrap(), 0.802519646908809);
+    }
+
+    #[test]
+    fn autocorrelation() {
+        let data = lcg();
+        assert_p_value(autocorrelation_test(&data, 1).unwrap(), 0.417916897498494);
+        assert_p_value(autocorrelation_test(&data, 8).unwrap(), 0.904445101538714);
+    }
+
+    #[test]
+    fn rejects_short_blocks() {
+        let data = BitsData::from_text("1011001110".to_string());
+        assert!(binary_derivation_test(&data, 10).is_err());
+        assert!(autocorrelation_test(&data, 10).is_err());
+        assert!(autocorrelation_test(&data, 16).is_err());
+    }
+}
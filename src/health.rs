@@ -0,0 +1,373 @@
+//! SP 800-90B continuous health tests (section 4.4) run over the decoded input stream,
+//! before the bits are unpacked and the region is selected. They cover the input from its start
+//! to the end of the last tested block, bytes read ahead of it are dropped.
+//!
+//! Samples are stored one per byte, a sample of `w` bits is the lowest `w` bits of the byte.
+
+use serde::{Deserialize, Serialize};
+use statrs::distribution::{Binomial, DiscreteCDF};
+
+/// Probability of a false positive of a health test, 2^-20.
+const ALPHA_LOG2: f64 = 20_f64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HealthConfig {
+    /// Claimed min-entropy of a sample in bits.
+    pub entropy: f64,
+    pub sample_bits: usize,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            entropy: 8_f64,
+            sample_bits: 8,
+        }
+    }
+}
+
+impl HealthConfig {
+    pub fn check(&self) -> Result<(), String> {
+        if !(1..=8).contains(&self.sample_bits) {
+            return Err("Sample width must be from 1 to 8 bits".to_string());
+        }
+        if !(self.entropy > 0_f64 && self.entropy <= self.sample_bits as f64) {
+            return Err(format!(
+                "Claimed entropy must be in (0, {}] bits",
+                self.sample_bits
+            ));
+        }
+        Ok(())
+    }
+
+    /// Cutoff of the Repetition Count Test (4.4.1).
+    pub fn rct_cutoff(&self) -> usize {
+        1 + (ALPHA_LOG2 / self.entropy).ceil() as usize
+    }
+
+    /// Window of the Adaptive Proportion Test (4.4.2).
+    pub fn apt_window(&self) -> usize {
+        if self.sample_bits == 1 {
+            1024
+        } else {
+            512
+        }
+    }
+
+    /// Cutoff of the Adaptive Proportion Test: `1 + CRITBINOM(W, 2^-H, 1 - alpha)`.
+    pub fn apt_cutoff(&self) -> usize {
+        let window = self.apt_window();
+        let binomial = Binomial::new(2_f64.powf(-self.entropy), window as u64).unwrap();
+        let q = 1_f64 - 2_f64.powf(-ALPHA_LOG2);
+        let critbinom = (0..window)
+            .find(|k| binomial.cdf(*k as u64) >= q)
+            .unwrap_or(window);
+        (1 + critbinom).min(window)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum HealthTest {
+    #[serde(rename = "rct")]
+    RepetitionCount,
+    #[serde(rename = "apt")]
+    AdaptiveProportion,
+}
+
+impl HealthTest {
+    pub fn name(self) -> &'static str {
+        match self {
+            HealthTest::RepetitionCount => "Repetition Count",
+            HealthTest::AdaptiveProportion => "Adaptive Proportion",
+        }
+    }
+}
+
+/// The sample at `offset` reached the cutoff of `test`.
+#[derive(Clone, Debug, Serialize)]
+pub struct Violation {
+    pub test: HealthTest,
+    /// Offset of the sample in the decoded input in bytes, the offset in the file for raw input.
+    /// Bit order, word size and the region don't change it.
+    pub offset: u64,
+    pub sample: u8,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct HealthReport {
+    pub config: HealthConfig,
+    pub rct_cutoff: usize,
+    pub apt_window: usize,
+    pub apt_cutoff: usize,
+    /// Number of tested samples, the bytes from the start of the decoded input
+    /// to the end of the last tested block.
+    pub samples: u64,
+    pub violations: Vec<Violation>,
+}
+
+impl HealthReport {
+    pub fn passed(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    pub fn count(&self, test: HealthTest) -> usize {
+        self.violations.iter().filter(|v| v.test == test).count()
+    }
+}
+
+/// State of both tests, fed with consecutive parts of the input.
+pub struct HealthTests {
+    config: HealthConfig,
+    mask: u8,
+    rct_cutoff: usize,
+    apt_window: usize,
+    apt_cutoff: usize,
+    offset: u64,
+    /// The last sample and the length of its run.
+    rct: Option<(u8, usize)>,
+    /// The first sample of the window, its count and the number of samples in the window.
+    apt: Option<(u8, usize, usize)>,
+    violations: Vec<Violation>,
+}
+
+impl HealthTests {
+    pub fn new(config: HealthConfig) -> Self {
+        Self {
+            config,
+            mask: ((1_u16 << config.sample_bits) - 1) as u8,
+            rct_cutoff: config.rct_cutoff(),
+            apt_window: config.apt_window(),
+            apt_cutoff: config.apt_cutoff(),
+            offset: 0,
+            rct: None,
+            apt: None,
+            violations: vec![],
+        }
+    }
+
+    /// Tests the next bytes of the input. A run or a window reaching the cutoff is reported once.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            let sample = byte & self.mask;
+            let mut violation = |test| {
+                self.violations.push(Violation {
+                    test,
+                    offset: self.offset,
+                    sample,
+                })
+            };
+
+            let run = match self.rct {
+                Some((last, run)) if last == sample => run + 1,
+                _ => 1,
+            };
+            if run == self.rct_cutoff {
+                violation(HealthTest::RepetitionCount);
+            }
+            self.rct = Some((sample, run));
+
+            self.apt = match self.apt {
+                Some((first, count, len)) if len < self.apt_window => {
+                    let count = count + (first == sample) as usize;
+                    if count == self.apt_cutoff && first == sample {
+                        violation(HealthTest::AdaptiveProportion);
+                    }
+                    Some((first, count, len + 1))
+                }
+                _ => Some((sample, 1, 1)),
+            };
+
+            self.offset += 1;
+        }
+    }
+
+    pub fn finish(self) -> HealthReport {
+        self.finish_at(u64::MAX)
+    }
+
+    /// Report of the first `end` samples. A violation depends only on the samples before it,
+    /// so this is the report of the input cut at `end`.
+    pub fn finish_at(self, end: u64) -> HealthReport {
+        let samples = self.offset.min(end);
+        let mut violations = self.violations;
+        violations.retain(|v| v.offset < samples);
+        HealthReport {
+            config: self.config,
+            rct_cutoff: self.rct_cutoff,
+            apt_window: self.apt_window,
+            apt_cutoff: self.apt_cutoff,
+            samples,
+            violations,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battery::{Battery, BatteryConfig};
+    use crate::input::{BitOrder, Region};
+
+    fn config(entropy: f64, sample_bits: usize) -> HealthConfig {
+        HealthConfig {
+            entropy,
+            sample_bits,
+        }
+    }
+
+    #[test]
+    fn cutoffs() {
+        // SP 800-90B, section 4.4.1 and table 2 of section 4.4.2.
+        assert_eq!(config(1_f64, 1).rct_cutoff(), 21);
+        assert_eq!(config(8_f64, 8).rct_cutoff(), 4);
+        assert_eq!(config(1_f64, 1).apt_window(), 1024);
+        assert_eq!(config(1_f64, 1).apt_cutoff(), 589);
+        assert_eq!(config(1_f64, 8).apt_cutoff(), 311);
+        assert_eq!(config(2_f64, 8).apt_cutoff(), 177);
+        assert_eq!(config(4_f64, 8).apt_cutoff(), 62);
+        assert_eq!(config(8_f64, 8).apt_cutoff(), 13);
+    }
+
+    #[test]
+    fn checks_config() {
+        assert!(config(8_f64, 8).check().is_ok());
+        assert!(config(0.5, 1).check().is_ok());
+        assert!(config(2_f64, 1).check().is_err());
+        assert!(config(0_f64, 8).check().is_err());
+        assert!(config(1_f64, 9).check().is_err());
+    }
+
+    #[test]
+    fn reports_repetition_once() {
+        let mut tests = HealthTests::new(config(8_f64, 8));
+        tests.feed(&[1, 2, 2, 2]);
+        tests.feed(&[2, 2, 2, 3, 3]);
+        let report = tests.finish();
+        assert_eq!(report.samples, 9);
+        assert_eq!(report.count(HealthTest::RepetitionCount), 1);
+        assert_eq!(report.count(HealthTest::AdaptiveProportion), 0);
+        assert_eq!(report.violations[0].offset, 4);
+        assert_eq!(report.violations[0].sample, 2);
+    }
+
+    #[test]
+    fn reports_proportion() {
+        // Every other sample is 7, the 13th one is at offset 24.
+        let bytes: Vec<u8> = (0..512_usize)
+            .map(|i| if i % 2 == 0 { 7 } else { (i % 200) as u8 + 10 })
+            .collect();
+        let mut tests = HealthTests::new(config(8_f64, 8));
+        tests.feed(&bytes);
+        let report = tests.finish();
+        assert!(!report.passed());
+        assert_eq!(report.count(HealthTest::RepetitionCount), 0);
+        assert_eq!(report.count(HealthTest::AdaptiveProportion), 1);
+        assert_eq!(report.violations[0].offset, 24);
+    }
+
+    #[test]
+    fn masks_samples() {
+        // Only the lowest bit is a sample, 0x02 and 0x04 are both 0.
+        let mut tests = HealthTests::new(config(1_f64, 1));
+        tests.feed(&[0x02, 0x04].repeat(10));
+        tests.feed(&[0x00]);
+        let report = tests.finish();
+        assert_eq!(report.count(HealthTest::RepetitionCount), 1);
+        assert_eq!(report.violations[0].offset, 20);
+    }
+
+    #[test]
+    fn cuts_samples() {
+        let mut tests = HealthTests::new(config(8_f64, 8));
+        tests.feed(&[1, 2, 2, 2, 2, 3, 4, 4, 4, 4]);
+        let report = tests.finish_at(8);
+        assert_eq!(report.samples, 8);
+        assert_eq!(report.count(HealthTest::RepetitionCount), 1);
+        assert_eq!(report.violations[0].offset, 4);
+
+        let mut tests = HealthTests::new(config(8_f64, 8));
+        tests.feed(&[1, 2]);
+        assert_eq!(tests.finish_at(8).samples, 2);
+    }
+
+    #[test]
+    fn passes_varied_samples() {
+        let bytes: Vec<u8> = (0..4096_u32).map(|i| (i * 97 + 13) as u8).collect();
+        let mut tests = HealthTests::new(config(8_f64, 8));
+        bytes.chunks(100).for_each(|chunk| tests.feed(chunk));
+        let report = tests.finish();
+        assert!(report.passed());
+        assert_eq!(report.samples, 4096);
+    }
+
+    /// 10000 bytes without repeated neighbours, with a run of 5 equal bytes at `run`.
+    fn input(run: usize) -> Vec<u8> {
+        let mut input: Vec<u8> = (0..10_000_usize).map(|i| (i * 97 + 13) as u8).collect();
+        input[run..run + 5].fill(0);
+        input
+    }
+
+    /// Health tests of a run without tests on blocks of `n_bits`.
+    fn health(input: &[u8], n_bits: usize, n_blocks: usize, region: Region) -> HealthReport {
+        let mut config = BatteryConfig::new(n_bits, n_blocks);
+        config.tests.iter_mut().for_each(|test| test.enable = false);
+        config.health = Some(HealthConfig::default());
+        config.region = region;
+        Battery::new(config)
+            .run(input)
+            .unwrap()
+            .unwrap()
+            .health
+            .unwrap()
+    }
+
+    #[test]
+    fn health_stops_at_last_block() {
+        // Two blocks of 1000 bytes, the input read ahead isn't tested.
+        let report = health(&input(5000), 8000, 2, Region::default());
+        assert_eq!(report.samples, 2000);
+        assert!(report.passed());
+
+        let report = health(&input(1500), 8000, 2, Region::default());
+        assert_eq!(report.samples, 2000);
+        assert_eq!(report.count(HealthTest::RepetitionCount), 1);
+        assert_eq!(report.violations[0].offset, 1503);
+    }
+
+    #[test]
+    fn health_covers_skipped_bits() {
+        // Offset 1000 bytes, block, skip 1000 bytes, block.
+        let region = Region {
+            offset: 8000,
+            length: None,
+            skip: 8000,
+        };
+        let report = health(&input(500), 8000, 2, region);
+        assert_eq!(report.samples, 4000);
+        assert_eq!(report.violations[0].offset, 503);
+        assert!(health(&input(4000), 8000, 2, region).passed());
+    }
+
+    #[test]
+    fn health_ends_at_word() {
+        let mut config = BatteryConfig::new(12, 1);
+        config.tests.iter_mut().for_each(|test| test.enable = false);
+        config.health = Some(HealthConfig::default());
+        config.word_bits = 32;
+        let report = Battery::new(config).run(&input(5000)[..]).unwrap().unwrap();
+        assert_eq!(report.health.unwrap().samples, 4);
+    }
+
+    #[test]
+    fn health_ignores_unpacked_read_ahead() {
+        // Reversing bits reads ahead of the blocks.
+        let mut config = BatteryConfig::new(8000, 2);
+        config.tests.iter_mut().for_each(|test| test.enable = false);
+        config.health = Some(HealthConfig::default());
+        config.bit_order = BitOrder::Lsb;
+        let report = Battery::new(config).run(&input(3000)[..]).unwrap().unwrap();
+        let health = report.health.unwrap();
+        assert_eq!(health.samples, 2000);
+        assert!(health.passed());
+    }
+}
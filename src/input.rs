@@ -0,0 +1,906 @@
+//! Formats of the input: raw bytes or text encodings of bits. Text is decoded to bytes,
+//! so blocks are counted in decoded bits; whitespace and line breaks are ignored.
+//! Decoded bytes are then unpacked to bits in the selected bit order and word size.
+
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use nistrs::BitsData;
+use serde::{Deserialize, Serialize};
+
+/// Number of bytes read to detect the format.
+const DETECT_BYTES: usize = 4096;
+
+/// Path of the standard input on the command line.
+pub const STDIN: &str = "-";
+
+/// Supported sizes of a word in bits.
+pub const WORD_BITS: [usize; 4] = [8, 16, 32, 64];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputFormat {
+    /// Raw bytes are read as they are, whatever they look like.
+    #[default]
+    Raw,
+    /// Detected from the beginning of the input, only when selected.
+    Auto,
+    /// Characters '0' and '1', one per bit.
+    Ascii,
+    /// Hexadecimal digits, the first digit is the high half of a byte.
+    Hex,
+    /// Base64, standard or URL-safe alphabet, padding is optional.
+    Base64,
+}
+
+impl InputFormat {
+    pub const ALL: [InputFormat; 5] = [
+        InputFormat::Raw,
+        InputFormat::Auto,
+        InputFormat::Ascii,
+        InputFormat::Hex,
+        InputFormat::Base64,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            InputFormat::Auto => "Auto",
+            InputFormat::Raw => "Raw binary",
+            InputFormat::Ascii => "ASCII bits",
+            InputFormat::Hex => "Hex text",
+            InputFormat::Base64 => "Base64",
+        }
+    }
+
+    /// Name used on the command line.
+    pub fn id(self) -> &'static str {
+        match self {
+            InputFormat::Auto => "auto",
+            InputFormat::Raw => "raw",
+            InputFormat::Ascii => "ascii",
+            InputFormat::Hex => "hex",
+            InputFormat::Base64 => "base64",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.id().eq_ignore_ascii_case(id))
+    }
+
+    /// The narrowest text format all characters of `sample` belong to, raw otherwise.
+    pub fn detect(sample: &[u8]) -> Self {
+        let chars: Vec<u8> = sample
+            .iter()
+            .copied()
+            .filter(|c| !c.is_ascii_whitespace())
+            .collect();
+
+        if chars.is_empty() {
+            InputFormat::Raw
+        } else if chars.iter().all(|c| matches!(c, b'0' | b'1')) {
+            InputFormat::Ascii
+        } else if chars.iter().all(u8::is_ascii_hexdigit) {
+            InputFormat::Hex
+        } else if chars
+            .iter()
+            .all(|c| base64_value(*c).is_some() || *c == b'=')
+        {
+            InputFormat::Base64
+        } else {
+            InputFormat::Raw
+        }
+    }
+
+    /// Bits encoded by a character of a text format, `None` for whitespace.
+    fn decode(self, c: u8) -> Option<Result<(u32, u32), ()>> {
+        if c.is_ascii_whitespace() {
+            return None;
+        }
+
+        let value = match self {
+            InputFormat::Ascii => match c {
+                b'0' | b'1' => Some(((c - b'0') as u32, 1)),
+                _ => None,
+            },
+            InputFormat::Hex => (c as char).to_digit(16).map(|v| (v, 4)),
+            InputFormat::Base64 if c == b'=' => Some((0, 0)),
+            InputFormat::Base64 => base64_value(c).map(|v| (v, 6)),
+            InputFormat::Auto | InputFormat::Raw => None,
+        };
+        Some(value.ok_or(()))
+    }
+}
+
+fn base64_value(c: u8) -> Option<u32> {
+    match c {
+        b'A'..=b'Z' => Some((c - b'A') as u32),
+        b'a'..=b'z' => Some((c - b'a') as u32 + 26),
+        b'0'..=b'9' => Some((c - b'0') as u32 + 52),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None,
+    }
+}
+
+/// Reads the bytes encoded by the input in `format`. Reads fill the buffer unless the input ends,
+/// trailing bits of text which don't make a byte are dropped.
+pub struct Decoder<R> {
+    inner: R,
+    format: InputFormat,
+    /// Input read ahead, e.g. to detect the format.
+    input: Vec<u8>,
+    input_pos: usize,
+    /// Offset of the next input byte.
+    offset: u64,
+    bits: u32,
+    n_bits: u32,
+}
+
+impl<R: Read> Decoder<R> {
+    pub fn new(inner: R, format: InputFormat) -> Self {
+        Self {
+            inner,
+            format,
+            input: vec![],
+            input_pos: 0,
+            offset: 0,
+            bits: 0,
+            n_bits: 0,
+        }
+    }
+
+    /// The format of the input, detected on the first read for [`InputFormat::Auto`].
+    pub fn format(&self) -> InputFormat {
+        self.format
+    }
+
+    /// Reads the next chunk of the input, returns `false` at the end.
+    fn fill(&mut self, size: usize) -> io::Result<bool> {
+        self.input.resize(size, 0);
+        self.input_pos = 0;
+        let mut len = 0;
+        while len < size {
+            match self.inner.read(&mut self.input[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.input.truncate(len);
+        Ok(len > 0)
+    }
+
+    fn detect(&mut self) -> io::Result<()> {
+        if self.format == InputFormat::Auto {
+            self.fill(DETECT_BYTES)?;
+            self.format = InputFormat::detect(&self.input);
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.detect()?;
+
+        let mut len = 0;
+        while len < buf.len() {
+            if self.input_pos == self.input.len() && !self.fill(DETECT_BYTES.max(buf.len()))? {
+                break;
+            }
+
+            if self.format == InputFormat::Raw {
+                let n = (self.input.len() - self.input_pos).min(buf.len() - len);
+                buf[len..len + n].copy_from_slice(&self.input[self.input_pos..self.input_pos + n]);
+                self.input_pos += n;
+                self.offset += n as u64;
+                len += n;
+                continue;
+            }
+
+            while len < buf.len() && self.input_pos < self.input.len() {
+                let c = self.input[self.input_pos];
+                match self.format.decode(c) {
+                    None => {}
+                    // Base64 padding ends a quantum, its bits which don't make a byte are dropped.
+                    Some(Ok((_, 0))) => {
+                        self.bits = 0;
+                        self.n_bits = 0;
+                    }
+                    Some(Ok((value, n))) => {
+                        self.bits = (self.bits << n) | value;
+                        self.n_bits += n;
+                        if self.n_bits >= u8::BITS {
+                            self.n_bits -= u8::BITS;
+                            buf[len] = (self.bits >> self.n_bits) as u8;
+                            self.bits &= (1 << self.n_bits) - 1;
+                            len += 1;
+                        }
+                    }
+                    Some(Err(())) => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!(
+                                "invalid character {:?} at offset {} for {} input",
+                                c as char,
+                                self.offset,
+                                self.format.name()
+                            ),
+                        ))
+                    }
+                }
+                self.input_pos += 1;
+                self.offset += 1;
+            }
+        }
+
+        Ok(len)
+    }
+}
+
+/// Order of bits in a byte.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BitOrder {
+    /// The most significant bit is the first.
+    #[default]
+    Msb,
+    Lsb,
+}
+
+impl BitOrder {
+    pub const ALL: [BitOrder; 2] = [BitOrder::Msb, BitOrder::Lsb];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BitOrder::Msb => "MSB first",
+            BitOrder::Lsb => "LSB first",
+        }
+    }
+
+    /// Name used on the command line.
+    pub fn id(self) -> &'static str {
+        match self {
+            BitOrder::Msb => "msb",
+            BitOrder::Lsb => "lsb",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|order| order.id().eq_ignore_ascii_case(id))
+    }
+}
+
+pub fn check_word_bits(word_bits: usize) -> Result<(), String> {
+    if WORD_BITS.contains(&word_bits) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Word size must be one of {:?} bits, got {}",
+            WORD_BITS, word_bits
+        ))
+    }
+}
+
+/// Reorders bytes read from `inner`: bytes of every word of `word_bits` are swapped,
+/// then bits of every byte are put in `bit_order`. A trailing partial word is dropped.
+pub struct Unpacker<R> {
+    inner: R,
+    bit_order: BitOrder,
+    word_bytes: usize,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> Unpacker<R> {
+    pub fn new(inner: R, bit_order: BitOrder, word_bits: usize) -> Self {
+        Self {
+            inner,
+            bit_order,
+            word_bytes: word_bits / u8::BITS as usize,
+            buf: vec![],
+            pos: 0,
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Reads and reorders at least `size` bytes, returns `false` at the end.
+    fn fill(&mut self, size: usize) -> io::Result<bool> {
+        let size = DETECT_BYTES.max(size).next_multiple_of(self.word_bytes);
+        self.buf.resize(size, 0);
+        self.pos = 0;
+        let mut len = 0;
+        while len < size {
+            match self.inner.read(&mut self.buf[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.buf.truncate(len - len % self.word_bytes);
+
+        if self.word_bytes > 1 {
+            self.buf
+                .chunks_exact_mut(self.word_bytes)
+                .for_each(|word| word.reverse());
+        }
+        if self.bit_order == BitOrder::Lsb {
+            self.buf
+                .iter_mut()
+                .for_each(|byte| *byte = byte.reverse_bits());
+        }
+        Ok(!self.buf.is_empty())
+    }
+}
+
+impl<R: Read> Read for Unpacker<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.word_bytes == 1 && self.bit_order == BitOrder::Msb {
+            return self.inner.read(buf);
+        }
+
+        let mut len = 0;
+        while len < buf.len() {
+            if self.pos == self.buf.len() && !self.fill(buf.len() - len)? {
+                break;
+            }
+            let n = (self.buf.len() - self.pos).min(buf.len() - len);
+            buf[len..len + n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+            self.pos += n;
+            len += n;
+        }
+        Ok(len)
+    }
+}
+
+/// Part of the decoded input to test, in bits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Region {
+    /// Bits skipped at the start.
+    pub offset: u64,
+    /// Maximum number of bits after `offset`, `None` - up to the end.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length: Option<u64>,
+    /// Bits skipped after every block.
+    pub skip: u64,
+}
+
+impl Region {
+    pub fn is_whole(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Number of whole blocks of `n_bits` in the input of `bits`.
+    pub fn blocks(&self, bits: u64, n_bits: usize) -> usize {
+        let bits = bits.saturating_sub(self.offset);
+        let bits = self.length.map_or(bits, |length| length.min(bits));
+        ((bits + self.skip) / (n_bits as u64 + self.skip)) as usize
+    }
+}
+
+/// Passes every byte read from `inner` to `on_bytes`.
+pub struct Tap<R, F> {
+    inner: R,
+    on_bytes: F,
+}
+
+impl<R: Read, F: FnMut(&[u8])> Tap<R, F> {
+    pub fn new(inner: R, on_bytes: F) -> Self {
+        Self { inner, on_bytes }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+}
+
+impl<R: Read, F: FnMut(&[u8])> Read for Tap<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        (self.on_bytes)(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Splits the input into blocks of any number of bits, a block may start inside a byte.
+pub struct BlockReader<R> {
+    inner: R,
+    region: Region,
+    /// Bits left in the region, `None` - up to the end.
+    left: Option<u64>,
+    /// The lowest `n_rest` bits of the last byte read, they start the next block.
+    rest: u8,
+    n_rest: usize,
+    bits_read: u64,
+    blocks_read: usize,
+    /// Bytes read from `inner`.
+    input_bytes: u64,
+    /// Bytes read from `inner` up to the end of the last complete block.
+    block_end: u64,
+}
+
+impl<R: Read> BlockReader<R> {
+    pub fn new(inner: R, region: Region) -> Self {
+        Self {
+            inner,
+            region,
+            left: region.length,
+            rest: 0,
+            n_rest: 0,
+            bits_read: 0,
+            blocks_read: 0,
+            input_bytes: 0,
+            block_end: 0,
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Number of bits read from the input, including an incomplete last block.
+    pub fn bits_read(&self) -> u64 {
+        self.bits_read
+    }
+
+    /// Number of complete blocks read.
+    pub fn blocks_read(&self) -> usize {
+        self.blocks_read
+    }
+
+    /// Number of input bytes up to the end of the last complete block, including skipped bits.
+    /// The last byte may be partly used.
+    pub fn block_end(&self) -> u64 {
+        self.block_end
+    }
+
+    /// Reads the next block of `n_bits`, `Ok(None)` if the input or the region ends before
+    /// the block is complete.
+    pub fn read_block(&mut self, n_bits: usize) -> io::Result<Option<BitsData>> {
+        // The length of the region is counted after the offset.
+        let (skip, charged) = if self.blocks_read == 0 {
+            (self.region.offset, 0)
+        } else {
+            (self.region.skip, self.region.skip)
+        };
+        let needed = charged + n_bits as u64;
+        if self.left.is_some_and(|left| left < needed) || !self.skip(skip)? {
+            return Ok(None);
+        }
+        self.left = self.left.map(|left| left - needed);
+
+        let n_new = n_bits.saturating_sub(self.n_rest);
+        let mut buf = vec![0_u8; n_new.div_ceil(u8::BITS as usize)];
+        let mut len = 0;
+        while len < buf.len() {
+            match self.inner.read(&mut buf[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.bits_read += len as u64 * u8::BITS as u64;
+        self.input_bytes += len as u64;
+        if len < buf.len() {
+            return Ok(None);
+        }
+        self.blocks_read += 1;
+        self.block_end = self.input_bytes;
+
+        if self.n_rest == 0 && n_bits.is_multiple_of(u8::BITS as usize) {
+            return Ok(Some(BitsData::from_binary(buf)));
+        }
+
+        let mut text = String::with_capacity(self.n_rest + buf.len() * u8::BITS as usize);
+        (0..self.n_rest)
+            .rev()
+            .for_each(|i| text.push(if self.rest >> i & 1 == 1 { '1' } else { '0' }));
+        buf.iter()
+            .for_each(|byte| write!(text, "{:08b}", byte).unwrap());
+
+        // A block shorter than the rest takes no new bytes, the rest keeps its lowest bits.
+        let n_left = text.len() - n_bits;
+        let last = buf.last().copied().unwrap_or(self.rest);
+        self.rest = last & ((1_u16 << n_left) - 1) as u8;
+        self.n_rest = n_left;
+        text.truncate(n_bits);
+        Ok(Some(BitsData::from_text(text)))
+    }
+
+    /// Drops `n_bits` of the input, returns `false` if it ends.
+    fn skip(&mut self, n_bits: u64) -> io::Result<bool> {
+        if n_bits == 0 {
+            return Ok(true);
+        }
+
+        if n_bits <= self.n_rest as u64 {
+            self.n_rest -= n_bits as usize;
+            self.rest &= ((1_u16 << self.n_rest) - 1) as u8;
+            return Ok(true);
+        }
+
+        let n_bits = n_bits - self.n_rest as u64;
+        let bytes = n_bits.div_ceil(u8::BITS as u64);
+        let mut last = [0_u8];
+        if bytes > 1 {
+            let copied = io::copy(&mut (&mut self.inner).take(bytes - 1), &mut io::sink())?;
+            self.input_bytes += copied;
+            if copied < bytes - 1 {
+                return Ok(false);
+            }
+        }
+        loop {
+            match self.inner.read(&mut last) {
+                Ok(0) => return Ok(false),
+                Ok(_) => {
+                    self.input_bytes += 1;
+                    break;
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.n_rest = (bytes * u8::BITS as u64 - n_bits) as usize;
+        self.rest = last[0] & ((1_u16 << self.n_rest) - 1) as u8;
+        Ok(true)
+    }
+}
+
+/// The input is read as a stream of unknown size: the standard input, a named pipe or a device.
+pub fn is_stream(path: &Path) -> bool {
+    path.as_os_str() == STDIN || path.metadata().is_ok_and(|m| is_stream_type(m.file_type()))
+}
+
+#[cfg(unix)]
+fn is_stream_type(file_type: std::fs::FileType) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    file_type.is_fifo() || file_type.is_char_device() || file_type.is_block_device()
+}
+
+#[cfg(not(unix))]
+fn is_stream_type(_: std::fs::FileType) -> bool {
+    false
+}
+
+/// Number of bits the file `path` holds in `format`, whole words of `word_bits` only.
+/// Fails for a stream, it would be consumed.
+pub fn input_bits(path: &Path, format: InputFormat, word_bits: usize) -> io::Result<u64> {
+    if is_stream(path) {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the size of a stream is unknown",
+        ));
+    }
+    if path.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "it is a directory",
+        ));
+    }
+    let bits = decoded_bits(path, format)?;
+    Ok(bits - bits % word_bits as u64)
+}
+
+fn decoded_bits(path: &Path, format: InputFormat) -> io::Result<u64> {
+    let file = File::open(path)?;
+    if format == InputFormat::Raw {
+        return Ok(file.metadata()?.len() * u8::BITS as u64);
+    }
+
+    let mut decoder = Decoder::new(file, format);
+    decoder.detect()?;
+    if decoder.format() == InputFormat::Raw {
+        return Ok(path.metadata()?.len() * u8::BITS as u64);
+    }
+
+    let mut buf = vec![0_u8; 1 << 16];
+    let mut bytes = 0_u64;
+    loop {
+        match decoder.read(&mut buf)? {
+            0 => break,
+            n => bytes += n as u64,
+        }
+    }
+    Ok(bytes * u8::BITS as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(input: &[u8], format: InputFormat) -> io::Result<Vec<u8>> {
+        let mut out = vec![];
+        Decoder::new(input, format).read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    fn unpack(input: &[u8], bit_order: BitOrder, word_bits: usize) -> Vec<u8> {
+        let mut out = vec![];
+        Unpacker::new(input, bit_order, word_bits)
+            .read_to_end(&mut out)
+            .unwrap();
+        out
+    }
+
+    fn text(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:08b}", byte)).collect()
+    }
+
+    fn block_text(block: &BitsData) -> String {
+        block
+            .iter()
+            .map(|bit| if *bit { '1' } else { '0' })
+            .collect()
+    }
+
+    /// Reads blocks of `n_bits` up to the end of the input.
+    fn blocks(input: &[u8], region: Region, n_bits: usize) -> Vec<String> {
+        let mut reader = BlockReader::new(input, region);
+        let mut blocks = vec![];
+        while let Some(block) = reader.read_block(n_bits).unwrap() {
+            blocks.push(block_text(&block));
+        }
+        blocks
+    }
+
+    #[test]
+    fn decodes_ascii() {
+        assert_eq!(
+            decode(b"0100 0001\n01000010\r\n", InputFormat::Ascii).unwrap(),
+            b"AB"
+        );
+        // Trailing bits which don't make a byte are dropped.
+        assert_eq!(
+            decode(b"0100000101000010101", InputFormat::Ascii).unwrap(),
+            b"AB"
+        );
+    }
+
+    #[test]
+    fn decodes_hex() {
+        assert_eq!(
+            decode(b"41 42\n6a6B\n", InputFormat::Hex).unwrap(),
+            [0x41, 0x42, 0x6a, 0x6b]
+        );
+        assert_eq!(decode(b"414", InputFormat::Hex).unwrap(), b"A");
+    }
+
+    #[test]
+    fn decodes_base64() {
+        assert_eq!(
+            decode(b"QUJD\nREVG", InputFormat::Base64).unwrap(),
+            b"ABCDEF"
+        );
+        assert_eq!(decode(b"QUI=", InputFormat::Base64).unwrap(), b"AB");
+        assert_eq!(decode(b"QUI", InputFormat::Base64).unwrap(), b"AB");
+        assert_eq!(decode(b"-_8", InputFormat::Base64).unwrap(), [0xfb, 0xff]);
+        assert_eq!(decode(b"+/8=", InputFormat::Base64).unwrap(), [0xfb, 0xff]);
+    }
+
+    #[test]
+    fn decodes_padded_base64_chunks() {
+        assert_eq!(decode(b"QUI=QUI=", InputFormat::Base64).unwrap(), b"ABAB");
+        assert_eq!(
+            decode(b"QQ==\nQUJD\nQUI=\n", InputFormat::Base64).unwrap(),
+            b"AABCAB"
+        );
+        let chunk = b"AAEC/w==\n";
+        assert_eq!(
+            decode(&chunk.repeat(1000), InputFormat::Base64).unwrap(),
+            [0x00, 0x01, 0x02, 0xff].repeat(1000)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_characters() {
+        let e = decode(b"0101x", InputFormat::Ascii).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert!(e.to_string().contains("'x' at offset 4"), "{}", e);
+        assert!(decode(b"4g", InputFormat::Hex).is_err());
+        assert!(decode(b"QU*D", InputFormat::Base64).is_err());
+    }
+
+    #[test]
+    fn detects_format() {
+        assert_eq!(InputFormat::detect(b"0101 1100\n"), InputFormat::Ascii);
+        assert_eq!(InputFormat::detect(b"dead BEEF\n0123"), InputFormat::Hex);
+        assert_eq!(InputFormat::detect(b"QUJD+/=="), InputFormat::Base64);
+        assert_eq!(InputFormat::detect(&[0xff, 0x00, 0x30]), InputFormat::Raw);
+        assert_eq!(InputFormat::detect(b" \n"), InputFormat::Raw);
+        assert_eq!(InputFormat::detect(b""), InputFormat::Raw);
+    }
+
+    #[test]
+    fn decodes_detected_format() {
+        let mut decoder = Decoder::new(&b"41 42"[..], InputFormat::Auto);
+        let mut out = vec![];
+        decoder.read_to_end(&mut out).unwrap();
+        assert_eq!(out, b"AB");
+        assert_eq!(decoder.format(), InputFormat::Hex);
+
+        let raw = [0x00, 0xff, 0x80];
+        assert_eq!(decode(&raw, InputFormat::Auto).unwrap(), raw);
+    }
+
+    #[test]
+    fn reads_raw_by_default() {
+        // A raw capture which looks like text isn't decoded unless auto is selected.
+        assert_eq!(InputFormat::default(), InputFormat::Raw);
+        assert_eq!(decode(b"0110", InputFormat::default()).unwrap(), b"0110");
+        assert_eq!(decode(b"4142", InputFormat::default()).unwrap(), b"4142");
+    }
+
+    #[test]
+    fn swaps_words() {
+        let input = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+        assert_eq!(unpack(&input, BitOrder::Msb, 8), input);
+        assert_eq!(unpack(&input, BitOrder::Msb, 16), [2, 1, 4, 3, 6, 5, 8, 7]);
+        assert_eq!(unpack(&input, BitOrder::Msb, 32), [4, 3, 2, 1, 8, 7, 6, 5]);
+        assert_eq!(unpack(&input, BitOrder::Msb, 64), [8, 7, 6, 5, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn reverses_bits() {
+        assert_eq!(
+            unpack(&[0x01, 0x02, 0x03, 0xf0], BitOrder::Lsb, 8),
+            [0x80, 0x40, 0xc0, 0x0f]
+        );
+        assert_eq!(unpack(&[0x01, 0x02], BitOrder::Lsb, 16), [0x40, 0x80]);
+    }
+
+    #[test]
+    fn unpacks_long_input() {
+        let input: Vec<u8> = (0..3 * DETECT_BYTES + 5).map(|i| i as u8).collect();
+        let out = unpack(&input, BitOrder::Msb, 32);
+        assert_eq!(out.len(), input.len() - 1);
+        out.chunks(4)
+            .zip(input.chunks(4))
+            .for_each(|(word, original)| assert!(word.iter().eq(original.iter().rev())));
+    }
+
+    #[test]
+    fn reads_whole_bytes() {
+        assert_eq!(
+            blocks(&[0xab, 0xcd, 0xef], Region::default(), 8),
+            ["10101011", "11001101", "11101111"]
+        );
+    }
+
+    #[test]
+    fn reads_blocks_inside_bytes() {
+        let input = [0xab, 0xcd, 0xef];
+        assert_eq!(
+            blocks(&input, Region::default(), 12),
+            ["101010111100", "110111101111"]
+        );
+        assert_eq!(
+            blocks(&input, Region::default(), 10),
+            ["1010101111", "0011011110"]
+        );
+    }
+
+    #[test]
+    fn concatenated_blocks_are_the_input() {
+        let input: Vec<u8> = (0..64_u32).map(|i| (i * 37 + 11) as u8).collect();
+        let bits = text(&input);
+        for n_bits in [1, 3, 5, 7, 9, 13, 17] {
+            let blocks = blocks(&input, Region::default(), n_bits);
+            assert_eq!(blocks.len(), bits.len() / n_bits, "{} bits", n_bits);
+            assert_eq!(
+                blocks.concat(),
+                bits[..blocks.len() * n_bits],
+                "{} bits",
+                n_bits
+            );
+        }
+    }
+
+    #[test]
+    fn reads_region() {
+        let input: Vec<u8> = (0..20_u8).collect();
+        let bits = text(&input);
+
+        // The length is counted after the offset.
+        let region = Region {
+            offset: 8,
+            length: Some(64),
+            skip: 0,
+        };
+        let read = blocks(&input, region, 16);
+        assert_eq!(read.len(), 4);
+        assert_eq!(read.concat(), bits[8..72]);
+        assert_eq!(region.blocks(bits.len() as u64, 16), 4);
+
+        let region = Region {
+            offset: 1000 * 8,
+            length: Some(10_000 * 8),
+            skip: 0,
+        };
+        let input = vec![0x5a; 20_000];
+        assert_eq!(blocks(&input, region, 8000).len(), 10);
+        assert_eq!(region.blocks(input.len() as u64 * 8, 8000), 10);
+    }
+
+    #[test]
+    fn skips_between_blocks() {
+        let input = [0x12, 0x34, 0x56];
+        let bits = text(&input);
+        let region = Region {
+            offset: 4,
+            length: None,
+            skip: 4,
+        };
+        assert_eq!(blocks(&input, region, 4), ["0010", "0100", "0110"]);
+
+        let region = Region {
+            offset: 3,
+            length: Some(17),
+            skip: 2,
+        };
+        // Offset 3, then blocks at 3..8, 10..15 and 17..22 of which only two fit in 17 bits.
+        assert_eq!(blocks(&input, region, 5), [&bits[3..8], &bits[10..15]]);
+        assert_eq!(region.blocks(bits.len() as u64, 5), 2);
+    }
+
+    #[test]
+    fn counts_read_bits() {
+        let input = [0xff; 10];
+        let mut reader = BlockReader::new(&input[..], Region::default());
+        assert!(reader.read_block(48).unwrap().is_some());
+        assert!(reader.read_block(48).unwrap().is_none());
+        assert_eq!(reader.blocks_read(), 1);
+        assert_eq!(reader.bits_read(), 80);
+    }
+
+    #[test]
+    fn finds_block_end() {
+        let input = [0x5a; 20];
+        let mut reader = BlockReader::new(&input[..], Region::default());
+        reader.read_block(12).unwrap();
+        assert_eq!(reader.block_end(), 2);
+        reader.read_block(4).unwrap();
+        assert_eq!(reader.block_end(), 2);
+        assert!(reader.read_block(200).unwrap().is_none());
+        assert_eq!(reader.block_end(), 2);
+
+        let region = Region {
+            offset: 20,
+            length: None,
+            skip: 30,
+        };
+        // Blocks of bits 20..28 and 58..66.
+        let mut reader = BlockReader::new(&input[..], region);
+        reader.read_block(8).unwrap();
+        assert_eq!(reader.block_end(), 4);
+        reader.read_block(8).unwrap();
+        assert_eq!(reader.block_end(), 9);
+    }
+
+    #[test]
+    fn counts_region_blocks() {
+        let region = Region {
+            offset: 100,
+            length: None,
+            skip: 50,
+        };
+        assert_eq!(region.blocks(1000, 100), 6);
+        assert_eq!(region.blocks(50, 100), 0);
+        assert_eq!(Region::default().blocks(1000, 100), 10);
+    }
+}
@@ -0,0 +1,39 @@
+//! Engine of GuiNistRs: runs the [NIST SP 800-22](https://nvlpubs.nist.gov/nistpubs/Legacy/SP/nistspecialpublication800-22r1a.pdf)
+//! battery from [`nistrs`] over a stream of blocks and builds the second-level report.
+//!
+//! # Example usage:
+//!
+//! ```no_run
+//! use guinistrs::{Battery, BatteryConfig};
+//!
+//! let file = std::fs::File::open("data.bin").unwrap();
+//! if let Some(report) = Battery::new(BatteryConfig::new(1_000_000, 100)).run(file).unwrap() {
+//!     println!("{}", report.text);
+//! }
+//! ```
+
+extern crate rayon;
+
+pub mod ais31;
+pub mod battery;
+pub mod entropy;
+pub mod error;
+pub mod export;
+pub mod fips;
+pub mod gmt;
+pub mod health;
+pub mod input;
+pub mod presets;
+pub mod profile;
+pub mod report;
+pub mod session;
+pub mod tests;
+mod universal;
+pub mod validate;
+
+pub use battery::{start_thread, Battery, BatteryConfig, BatteryKind};
+pub use error::TestError;
+pub use profile::Profile;
+pub use report::{Report, Thresholds};
+pub use session::{Progress, RunEvent, RunHandle, RunResult};
+pub use tests::RandomnessTest;
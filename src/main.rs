@@ -5,28 +5,27 @@ use egui::plot::{Bar, BarChart, Line, LineStyle, Plot, PlotPoints, Points};
 use egui::{Label, RichText, ScrollArea, TextStyle};
 use egui_extras::{Size, StripBuilder};
 
-use std::fmt::Write as FmtWrite;
-use std::fs::File;
-use std::io::{Read, Write};
-use std::sync::mpsc::{channel, Receiver, Sender};
-use std::time::{Duration, Instant};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 
 use native_dialog::{FileDialog, MessageDialog, MessageType};
 
-use statrs::function::gamma::gamma_ur;
-
-use nistrs::prelude::*;
-use rayon::iter::{IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
-
-#[macro_use]
-extern crate lazy_static;
-
-extern crate rayon;
+use guinistrs::export::{self, ExportFormat};
+use guinistrs::input::{self, BitOrder, InputFormat, Region, WORD_BITS};
+use guinistrs::validate::{self, Issue};
+use guinistrs::{start_thread, BatteryConfig, Progress, Report, RunEvent, RunHandle, TestError};
 
+mod cli;
 mod configure_tests;
-mod tests;
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("run") {
+        std::process::exit(cli::run(&args[1..]));
+    }
+
     let options = eframe::NativeOptions {
         drag_and_drop_support: true,
 
@@ -44,122 +43,31 @@ fn main() {
 }
 
 struct GuiNist {
-    p_distr: [usize; 10],
-    p_p_distr: Vec<f64>,
-    tresh_p_p: (f64, f64),
-    result: String,
+    report: Report,
 
     path_to_file: String,
+    format: InputFormat,
+    bit_order: BitOrder,
+    word_bits: usize,
+    region: Region,
+    /// Offset, length and skip are shown in bits, otherwise in bytes.
+    region_in_bits: bool,
+    /// The file is a named pipe or a device.
+    stream: bool,
+    output: String,
+    timestamp: bool,
+    report_status: Option<Result<String, String>>,
     n_bits: usize,
     n_blocks: usize,
 
     view_configure: bool,
     configure: configure_tests::ConfigureTests,
 
-    receiver: Option<Receiver<Option<tests::ResultTestsStat>>>,
-}
-
-fn thread_test(
-    path: String,
-    n_bits: usize,
-    n_blocks: usize,
-    sender: Sender<Option<tests::ResultTestsStat>>,
-) {
-    let mut file = match File::open(&path) {
-        Ok(v) => v,
-        Err(e) => {
-            MessageDialog::new()
-                .set_type(MessageType::Error)
-                .set_title("Error open file!")
-                .set_text(&format!("Can't open file: {}", e))
-                .show_alert()
-                .unwrap();
-            sender.send(None).unwrap();
-            return;
-        }
-    };
-
-    let mut stat: tests::ResultTestsStat = Default::default();
-
-    let all_time = Instant::now();
-    let mut sum_time = u128::default();
-    for i in 0..n_blocks {
-        let local_time = Instant::now();
-
-        if *tests::STOP_FLAG.lock().unwrap() {
-            sender.send(None).unwrap();
-            return;
-        }
-
-        let mut buf = vec![0_u8; n_bits / u8::BITS as usize];
-        if let Err(e) = file.read(&mut buf[..]) {
-            MessageDialog::new()
-                .set_title("Error!")
-                .set_text(format!("Error read sequence: {}", e).as_str())
-                .show_alert()
-                .unwrap();
-        };
-
-        let data = BitsData::from_binary(buf);
-
-        let tests = *tests::TESTS.lock().unwrap();
-
-        stat.par_iter_mut().zip(tests).for_each(|(ls, nist)| {
-            if !nist.enable || *tests::STOP_FLAG.lock().unwrap() {
-                return;
-            }
-
-            match (nist.test_cb)(&data, nist.param) {
-                Ok(v) => {
-                    if ls.len() != v.len() {
-                        ls.resize(v.len(), tests::TestStat::default());
-                    }
-
-                    v.into_iter().zip(&mut *ls).for_each(|((pass, p_val), st)| {
-                        if pass {
-                            st.ratio += 1_f64;
-                        }
-
-                        let index = ((p_val * 10_f64).floor() as usize).min(9);
-                        st.p_distr[index] += 1;
-                    });
-                }
-                Err(Some(e)) => {
-                    eprintln!("ERROR {}::{}", nist.name, e)
-                }
-                Err(None) => {}
-            }
-        });
-
-        sum_time += local_time.elapsed().as_millis();
-
-        *tests::COMPLETE_BLOCKS.lock().unwrap() += 1;
-        *tests::AVR_TIME_TO_BLOCK.lock().unwrap() = ((sum_time as usize) / (i + 1)) as u128;
-
-        *tests::TOTAL_TIME.lock().unwrap() = all_time.elapsed();
-    }
-
-    sender.send(Some(stat)).unwrap();
-}
-
-fn start_thread(
-    path: String,
-    n_bits: usize,
-    n_blocks: usize,
-) -> Receiver<Option<tests::ResultTestsStat>> {
-    let (sender, receiver) = channel::<Option<tests::ResultTestsStat>>();
-
-    *tests::STOP_FLAG.lock().unwrap() = false;
-    *tests::COMPLETE_BLOCKS.lock().unwrap() = 0;
-    *tests::AVR_TIME_TO_BLOCK.lock().unwrap() = 0;
-    *tests::NUMBERS_OF_BLOCKS.lock().unwrap() = n_blocks;
-    *tests::TOTAL_TIME.lock().unwrap() = Duration::new(0, 0);
-
-    std::thread::spawn(move || {
-        thread_test(path, n_bits, n_blocks, sender);
-    });
-
-    receiver
+    run: Option<RunHandle>,
+    errors: Vec<TestError>,
+    progress: Arc<Progress>,
+    /// Issues found before the start, the run waits for the user's choice.
+    preflight: Vec<Issue>,
 }
 
 fn duration_string(dur: Duration) -> String {
@@ -179,124 +87,224 @@ fn duration_string(dur: Duration) -> String {
 impl GuiNist {
     fn new(_cc: &eframe::CreationContext<'_>) -> Self {
         Self {
-            p_distr: [0; 10],
-            p_p_distr: vec![],
-            tresh_p_p: (f64::default(), f64::default()),
+            report: Report::default(),
             path_to_file: String::new(),
-            result: String::new(),
+            format: InputFormat::default(),
+            bit_order: BitOrder::default(),
+            word_bits: u8::BITS as usize,
+            region: Region::default(),
+            region_in_bits: false,
+            stream: false,
+            output: String::new(),
+            timestamp: false,
+            report_status: None,
             n_bits: 1_000_000,
             n_blocks: 1_000,
-            receiver: None,
+            run: None,
+            errors: vec![],
+            progress: Arc::default(),
+            preflight: vec![],
             configure: configure_tests::ConfigureTests::default(),
             view_configure: false,
         }
     }
 
-    fn calc_stat(&mut self, stat: tests::ResultTestsStat) {
-        if *tests::STOP_FLAG.lock().unwrap() {
-            return;
-        }
-
-        self.result.clear();
-        self.p_distr.fill(0_usize);
-        self.tresh_p_p = (f64::MAX, f64::MIN);
-        self.p_p_distr.clear();
-
-        self.result = String::with_capacity(18700);
+    fn save_report(&mut self, report: Report) {
+        self.report = report;
 
-        let mut failed = usize::default();
-        let mut sum_min_p = 0_f64;
-        let mut sum_max_p = 0_f64;
-        let mut number_of_test = 0_usize;
+        let started_at = self.timestamp.then_some(self.report.info.started_at);
+        let path = export::report_path(&self.path_to_file, &self.output, started_at);
 
-        let tests = *tests::TESTS.lock().unwrap();
+        let mut result = export::export(&self.report, &path, ExportFormat::Text, false);
+        if matches!(&result, Err(e) if e.kind() == io::ErrorKind::AlreadyExists) {
+            let overwrite = MessageDialog::new()
+                .set_type(MessageType::Warning)
+                .set_title("Report exists")
+                .set_text(&format!("{} already exists. Overwrite it?", path.display()))
+                .show_confirm()
+                .unwrap();
 
-        tests.into_iter().zip(stat).for_each(|(test, st)| {
-            if !test.enable {
+            if !overwrite {
+                self.report_status = Some(Err("Report is not saved".to_string()));
                 return;
             }
 
-            const PA: f64 = 1_f64 - TEST_THRESHOLD;
-
-            for test_st in st {
-                let sample_size = test_st.p_distr.iter().sum::<usize>() as f64;
-                let p_range = 3_f64 * (PA * (1_f64 - PA) / sample_size).sqrt();
-                let min_p = PA - p_range;
-                let max_p = PA + p_range;
+            result = export::export(&self.report, &path, ExportFormat::Text, true);
+        }
 
-                sum_min_p += min_p;
-                sum_max_p += max_p;
-                number_of_test += 1;
+        self.report_status = Some(match result {
+            Ok(_) => Ok(format!("Report saved to {}", path.display())),
+            Err(e) => Err(format!("Can't write report to {}: {}", path.display(), e)),
+        });
+    }
+}
 
-                let c_tmp = (sample_size / 10_f64).floor() as isize;
+impl GuiNist {
+    /// Edits a size of the region in the selected unit, returns `true` if it was changed.
+    fn region_value(&self, ui: &mut egui::Ui, enabled: bool, value: &mut u64) -> bool {
+        let unit = if self.region_in_bits {
+            1
+        } else {
+            u8::BITS as u64
+        };
+        let mut v = *value / unit;
+        let changed = ui
+            .add_enabled(enabled, egui::DragValue::new(&mut v))
+            .changed();
+        if changed {
+            *value = v * unit;
+        }
+        changed
+    }
 
-                let mut chi_squad = f64::default();
-                self.p_distr
-                    .iter_mut()
-                    .zip(test_st.p_distr)
-                    .for_each(|(out, p)| {
-                        self.result += &format!("{:>5}", p);
-                        *out += p;
+    /// Number of whole blocks in the file, the input is decoded for text formats.
+    /// The size of a stream is unknown, its number of blocks is set by the user.
+    fn update_n_blocks(&mut self) {
+        let path = Path::new(&self.path_to_file);
+        self.stream = input::is_stream(path);
+        if self.stream {
+            return;
+        }
+        if let Ok(bits) = input::input_bits(path, self.format, self.word_bits) {
+            self.n_blocks = self.region.blocks(bits, self.n_bits);
+        }
+    }
 
-                        chi_squad += (p as isize - c_tmp).pow(2) as f64;
-                    });
+    /// Settings of the run set in the main window, the tests are set in the Configure window.
+    fn run_settings(&self) -> BatteryConfig {
+        BatteryConfig {
+            kind: self.configure.battery,
+            n_bits: self.n_bits,
+            n_blocks: self.n_blocks,
+            tests: vec![],
+            thresholds: self.configure.thresholds,
+            health: None,
+            format: self.format,
+            bit_order: self.bit_order,
+            word_bits: self.word_bits,
+            region: self.region,
+        }
+    }
 
-                chi_squad /= c_tmp as f64;
-                if chi_squad > 0_f64 && !chi_squad.is_infinite() {
-                    chi_squad = gamma_ur(9.0 / 2.0, chi_squad / 2.0);
-                } else {
-                    chi_squad = 0_f64;
-                }
+    /// Shows the Configure window, a loaded profile may change the settings of the run.
+    fn show_configure(&mut self, ctx: &egui::Context) {
+        let mut run = self.run_settings();
+        self.configure.show(ctx, &mut self.view_configure, &mut run);
+
+        self.n_bits = run.n_bits;
+        self.n_blocks = run.n_blocks;
+        self.format = run.format;
+        self.bit_order = run.bit_order;
+        self.word_bits = run.word_bits;
+        self.region = run.region;
+    }
 
-                let ratio = test_st.ratio / sample_size;
+    fn start(&mut self) {
+        let config = self.configure.config(&self.run_settings());
+        self.errors.clear();
+        self.report_status = None;
+        let run = start_thread(self.path_to_file.clone(), config);
+        self.progress = run.progress().clone();
+        self.run = Some(run);
+    }
 
-                let is_rand;
-                if ratio < min_p || ratio > max_p || chi_squad < 0.0001 {
-                    is_rand = false;
-                    failed += 1;
-                } else {
-                    is_rand = true;
-                }
+    /// Starts the run or asks what to do with the tests that don't fit the block size.
+    fn try_start(&mut self) {
+        self.preflight =
+            validate::validate(self.configure.battery, &self.configure.tests, self.n_bits);
+        if self.preflight.is_empty() {
+            self.start();
+        }
+    }
 
-                self.result += &format!("{:>12.5}{:>12.5}", chi_squad, ratio);
+    fn show_preflight(&mut self, ctx: &egui::Context) {
+        if self.preflight.is_empty() {
+            return;
+        }
 
-                if is_rand {
-                    self.result += "   ";
-                } else {
-                    self.result += " * ";
-                }
+        let mut choice = None;
+        egui::Window::new("Check parameters")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Some tests don't fit blocks of {} bits:",
+                    self.n_bits
+                ));
+                self.preflight.iter().for_each(|issue| {
+                    ui.label(issue.to_string());
+                });
 
-                self.result += test.name;
-                self.result += "\n";
+                ui.horizontal(|ui| {
+                    if ui.button("Adjust").clicked() {
+                        choice = Some(Some(false));
+                    }
+                    if ui.button("Disable").clicked() {
+                        choice = Some(Some(true));
+                    }
+                    if ui.button("Run anyway").clicked() {
+                        choice = Some(None);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.preflight.clear();
+                    }
+                });
+            });
 
-                self.p_p_distr.push(ratio);
+        if let Some(fix) = choice {
+            if let Some(disable) = fix {
+                validate::apply_fixes(&mut self.configure.tests, &self.preflight, disable);
             }
-        });
-
-        self.tresh_p_p = (
-            sum_min_p / number_of_test as f64,
-            sum_max_p / number_of_test as f64,
-        );
-
-        self.result += "\n";
-        write!(self.result, "Number of failed tests (*): {}", failed).unwrap();
+            self.preflight.clear();
+            self.start();
+        }
+    }
 
-        let report_file = format!("{}.txt", self.path_to_file);
-        match File::create(report_file) {
-            Ok(mut f) => {
-                f.write_all(self.result.as_bytes())
-                    .expect("Error write result");
-            }
-            Err(e) => {
-                eprintln!("Can't open file: {}", e);
-            }
-        };
+    fn export_report(&mut self) {
+        let path = FileDialog::new()
+            .add_filter("JSON", &["json"])
+            .add_filter("CSV", &["csv"])
+            .add_filter("Text", &["txt"])
+            .show_save_single_file()
+            .unwrap();
+
+        // The save dialog has already asked about overwriting.
+        if let Some(path) = path {
+            let format = ExportFormat::from_path(&path);
+            self.report_status = Some(match export::export(&self.report, &path, format, true) {
+                Ok(_) => Ok(format!("Report exported to {}", path.display())),
+                Err(e) => Err(format!("Can't export report to {}: {}", path.display(), e)),
+            });
+        }
     }
 }
 
 impl eframe::App for GuiNist {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        self.configure.show(ctx, &mut self.view_configure);
+        self.show_configure(ctx);
+
+        self.show_preflight(ctx);
+
+        if !self.errors.is_empty() {
+            egui::TopBottomPanel::bottom("errors")
+                .resizable(true)
+                .show(ctx, |ui| {
+                    ui.label(format!("Errors: {}", self.errors.len()));
+                    let row_height = ui.text_style_height(&TextStyle::Monospace);
+                    ScrollArea::vertical().show_rows(
+                        ui,
+                        row_height,
+                        self.errors.len(),
+                        |ui, rows| {
+                            self.errors[rows].iter().for_each(|e| {
+                                ui.label(
+                                    RichText::new(e.to_string()).text_style(TextStyle::Monospace),
+                                );
+                            });
+                        },
+                    );
+                });
+        }
 
         egui::CentralPanel::default().show(ctx, |ui| {
             StripBuilder::new(ui)
@@ -306,7 +314,7 @@ impl eframe::App for GuiNist {
                         self.build_plot_ui(builder);
                     });
 
-                    let enabled = self.receiver.is_some();
+                    let enabled = self.run.is_some();
 
                     strip.strip(|builder| {
                         builder.sizes(Size::relative(0.5), 2).vertical(|mut strip| {
@@ -314,7 +322,7 @@ impl eframe::App for GuiNist {
                                 ScrollArea::vertical().show(ui, |ui| {
                                     ui.add(
                                         Label::new(
-                                            RichText::new(&self.result)
+                                            RichText::new(&self.report.text)
                                                 .text_style(TextStyle::Monospace),
                                         )
                                         .wrap(false),
@@ -328,12 +336,27 @@ impl eframe::App for GuiNist {
                         });
                     });
 
-                    if let Some(recv) = &self.receiver {
-                        if let Ok(v) = recv.recv_timeout(Duration::from_millis(1)) {
-                            if let Some(res) = v {
-                                self.calc_stat(res);
+                    if let Some(run) = &self.run {
+                        while let Some(event) = run.poll(Duration::from_millis(1)) {
+                            match event {
+                                RunEvent::Error(e) => self.errors.push(e),
+                                RunEvent::Finished(v) => {
+                                    match *v {
+                                        Ok(Some(res)) => self.save_report(res),
+                                        Ok(None) => {}
+                                        Err(e) => {
+                                            MessageDialog::new()
+                                                .set_type(MessageType::Error)
+                                                .set_title("Error!")
+                                                .set_text(&e.to_string())
+                                                .show_alert()
+                                                .unwrap();
+                                        }
+                                    }
+                                    self.run = None;
+                                    break;
+                                }
                             }
-                            self.receiver = None;
                         }
                     }
                 });
@@ -345,7 +368,8 @@ impl GuiNist {
     fn build_plot_ui(&mut self, builder: StripBuilder<'_>) {
         builder.sizes(Size::relative(0.5), 2).vertical(|mut strip| {
             let bars = BarChart::new(
-                self.p_distr
+                self.report
+                    .p_distr
                     .iter()
                     .enumerate()
                     .map(|(x, y)| Bar::new(x as f64, *y as f64))
@@ -353,35 +377,66 @@ impl GuiNist {
             );
 
             let points = Points::new(PlotPoints::new(
-                self.p_p_distr
+                self.report
+                    .p_p_distr
                     .iter()
                     .enumerate()
                     .map(|(x, y)| [x as f64, *y])
                     .collect(),
-            ));
-
-            let line_max_theshold: PlotPoints = (0..self.p_p_distr.len())
-                .map(|i| [i as f64, self.tresh_p_p.1])
-                .collect();
+            ))
+            .name("Proportion");
+
+            let labels: Vec<String> = if self.report.estimates.is_empty() {
+                self.report.rows.iter().map(|r| r.full_name()).collect()
+            } else {
+                self.report
+                    .estimates
+                    .iter()
+                    .map(|e| e.name.clone())
+                    .collect()
+            };
+
+            // Each result has its own range, it depends on the sample size of the test.
+            let bound_line = |bound: fn(&(f64, f64)) -> f64| -> PlotPoints {
+                self.report
+                    .rows
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, row)| row.samples > 0)
+                    .flat_map(|(x, row)| {
+                        let y = bound(&row.proportion_bounds);
+                        [[x as f64 - 0.5, y], [x as f64 + 0.5, y]]
+                    })
+                    .collect()
+            };
 
-            let line_min_theshold: PlotPoints = (0..self.p_p_distr.len())
-                .map(|i| [i as f64, self.tresh_p_p.0])
-                .collect();
+            let line_max_theshold = bound_line(|b| b.1);
+            let line_min_theshold = bound_line(|b| b.0);
 
             strip.cell(|ui| {
                 Plot::new("P-value").show(ui, |plot_ui| plot_ui.bar_chart(bars));
             });
 
             strip.cell(|ui| {
-                Plot::new("P from P-value").show(ui, |plot_ui| {
-                    plot_ui.points(points);
-                    plot_ui.line(
-                        Line::new(line_min_theshold).style(LineStyle::Dashed { length: 0.5 }),
-                    );
-                    plot_ui.line(
-                        Line::new(line_max_theshold).style(LineStyle::Dashed { length: 0.5 }),
-                    );
-                });
+                Plot::new("P from P-value")
+                    .label_formatter(move |name, value| {
+                        let label = labels.get(value.x.round() as usize);
+                        match label {
+                            Some(label) if name == "Proportion" => {
+                                format!("{}\n{:.5}", label, value.y)
+                            }
+                            _ => format!("x = {:.0}\ny = {:.5}", value.x, value.y),
+                        }
+                    })
+                    .show(ui, |plot_ui| {
+                        plot_ui.points(points);
+                        plot_ui.line(
+                            Line::new(line_min_theshold).style(LineStyle::Dashed { length: 0.5 }),
+                        );
+                        plot_ui.line(
+                            Line::new(line_max_theshold).style(LineStyle::Dashed { length: 0.5 }),
+                        );
+                    });
             });
         });
     }
@@ -393,10 +448,17 @@ impl GuiNist {
                 strip.cell(|ui| {
                     ui.horizontal(|ui| {
                         ui.label("File: ");
-                        ui.add_enabled(
+                        let path = ui.add_enabled(
                             !enabled,
                             egui::TextEdit::singleline(&mut self.path_to_file),
                         );
+                        if path.changed() {
+                            self.stream = input::is_stream(Path::new(&self.path_to_file));
+                        }
+                        // Text input is decoded to count blocks, so not on every key.
+                        if path.lost_focus() {
+                            self.update_n_blocks();
+                        }
                         if ui
                             .add_enabled(!enabled, egui::Button::new("Open file"))
                             .clicked()
@@ -404,11 +466,75 @@ impl GuiNist {
                             let path = FileDialog::new().show_open_single_file().unwrap();
                             if let Some(v) = path {
                                 self.path_to_file = v.into_os_string().into_string().unwrap();
-                                let file = File::open(&self.path_to_file).unwrap();
-                                let file_size = file.metadata().unwrap().len() as usize * 8;
-                                self.n_blocks = file_size / self.n_bits;
+                                self.update_n_blocks();
+                            }
+                        }
+
+                        ui.add_enabled_ui(!enabled, |ui| {
+                            let mut format = self.format;
+                            egui::ComboBox::from_id_source("format")
+                                .selected_text(format.name())
+                                .show_ui(ui, |ui| {
+                                    InputFormat::ALL.into_iter().for_each(|v| {
+                                        ui.selectable_value(&mut format, v, v.name());
+                                    });
+                                });
+
+                            if format != self.format {
+                                self.format = format;
+                                self.update_n_blocks();
+                            }
+
+                            egui::ComboBox::from_id_source("bit_order")
+                                .selected_text(self.bit_order.name())
+                                .show_ui(ui, |ui| {
+                                    BitOrder::ALL.into_iter().for_each(|v| {
+                                        ui.selectable_value(&mut self.bit_order, v, v.name());
+                                    });
+                                });
+
+                            let mut word_bits = self.word_bits;
+                            egui::ComboBox::from_id_source("word_bits")
+                                .selected_text(format!("{}-bit words", word_bits))
+                                .show_ui(ui, |ui| {
+                                    WORD_BITS.into_iter().for_each(|v| {
+                                        ui.selectable_value(
+                                            &mut word_bits,
+                                            v,
+                                            format!("{}-bit words", v),
+                                        );
+                                    });
+                                });
+
+                            if word_bits != self.word_bits {
+                                self.word_bits = word_bits;
+                                self.update_n_blocks();
+                            }
+                        });
+                    });
+                });
+
+                strip.cell(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Report: ");
+                        ui.add_enabled(
+                            !enabled,
+                            egui::TextEdit::singleline(&mut self.output)
+                                .hint_text("next to the file"),
+                        );
+                        if ui
+                            .add_enabled(!enabled, egui::Button::new("Browse"))
+                            .clicked()
+                        {
+                            let path = FileDialog::new().show_open_single_dir().unwrap();
+                            if let Some(v) = path {
+                                self.output = v.to_string_lossy().into_owned();
                             }
                         }
+                        ui.add_enabled(
+                            !enabled,
+                            egui::Checkbox::new(&mut self.timestamp, "Timestamp"),
+                        );
                     });
                 });
 
@@ -416,13 +542,48 @@ impl GuiNist {
                     ui.horizontal(|ui| {
                         ui.label("Bits: ");
                         ui.add_enabled(!enabled, egui::DragValue::new(&mut self.n_bits));
+
+                        let mut region = self.region;
+                        ui.label("Offset: ");
+                        let mut changed = self.region_value(ui, !enabled, &mut region.offset);
+
+                        ui.add_enabled_ui(!enabled, |ui| {
+                            egui::ComboBox::from_id_source("region_unit")
+                                .selected_text(if self.region_in_bits { "bits" } else { "bytes" })
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut self.region_in_bits, false, "bytes");
+                                    ui.selectable_value(&mut self.region_in_bits, true, "bits");
+                                });
+                        });
+
+                        ui.label("Skip: ")
+                            .on_hover_text("Skipped after every block");
+                        changed |= self.region_value(ui, !enabled, &mut region.skip);
+
+                        if changed {
+                            self.region = region;
+                            self.update_n_blocks();
+                        }
                     });
                 });
 
                 strip.cell(|ui| {
                     ui.horizontal(|ui| {
                         ui.label("Blocks: ");
-                        ui.add_enabled(!enabled, egui::DragValue::new(&mut self.n_blocks));
+                        ui.add_enabled(!enabled, egui::DragValue::new(&mut self.n_blocks))
+                            .on_hover_text(if self.stream {
+                                "The size of a stream is unknown, set the number of blocks"
+                            } else {
+                                "Calculated from the size of the file"
+                            });
+
+                        let mut length = self.region.length.unwrap_or(0);
+                        ui.label("Length: ")
+                            .on_hover_text("0 - up to the end of the file");
+                        if self.region_value(ui, !enabled, &mut length) {
+                            self.region.length = (length > 0).then_some(length);
+                            self.update_n_blocks();
+                        }
                     });
                 });
 
@@ -432,15 +593,11 @@ impl GuiNist {
                             .add_enabled(!enabled, egui::Button::new("Start"))
                             .clicked()
                         {
-                            self.receiver = Some(start_thread(
-                                self.path_to_file.clone(),
-                                self.n_bits,
-                                self.n_blocks,
-                            ));
+                            self.try_start();
                         };
 
                         if ui.add_enabled(enabled, egui::Button::new("Stop")).clicked() {
-                            *tests::STOP_FLAG.lock().unwrap() = true;
+                            self.progress.stop();
                         }
 
                         if ui
@@ -448,16 +605,22 @@ impl GuiNist {
                             .clicked()
                         {
                             self.view_configure = true;
-                            self.configure.show(ctx, &mut self.view_configure);
+                            self.show_configure(ctx);
+                        }
+
+                        let has_report =
+                            !self.report.rows.is_empty() || !self.report.estimates.is_empty();
+                        if ui
+                            .add_enabled(!enabled && has_report, egui::Button::new("Export..."))
+                            .clicked()
+                        {
+                            self.export_report();
                         }
                     });
                 });
 
-                let avr = *tests::AVR_TIME_TO_BLOCK.lock().unwrap() as u64;
-                let blocks = *tests::COMPLETE_BLOCKS.lock().unwrap() as u64;
-                let n_blocks = *tests::NUMBERS_OF_BLOCKS.lock().unwrap() as u64;
-
-                let progress = blocks as f32 / n_blocks as f32;
+                let progress =
+                    self.progress.complete_blocks() as f32 / self.progress.n_blocks() as f32;
 
                 strip.cell(|ui| {
                     ui.add(
@@ -471,7 +634,7 @@ impl GuiNist {
                     ui.label(
                         RichText::new(format!(
                             "Avr time to block: {}",
-                            duration_string(Duration::from_millis(avr))
+                            duration_string(self.progress.avr_time_to_block())
                         ))
                         .text_style(TextStyle::Monospace),
                     );
@@ -481,9 +644,7 @@ impl GuiNist {
                     ui.label(
                         RichText::new(format!(
                             "        Time left: {}",
-                            duration_string(Duration::from_millis(
-                                avr * (n_blocks - blocks)
-                            ))
+                            duration_string(self.progress.time_left())
                         ))
                         .text_style(TextStyle::Monospace),
                     );
@@ -493,11 +654,21 @@ impl GuiNist {
                     ui.label(
                         RichText::new(format!(
                             "      Time passed: {}",
-                            duration_string(*tests::TOTAL_TIME.lock().unwrap())
+                            duration_string(self.progress.total_time())
                         ))
                         .text_style(TextStyle::Monospace),
                     );
                 });
+
+                strip.cell(|ui| match &self.report_status {
+                    Some(Ok(v)) => {
+                        ui.label(v);
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(egui::Color32::RED, e);
+                    }
+                    None => {}
+                });
             });
     }
 }
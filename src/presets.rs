@@ -0,0 +1,182 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::battery::{BatteryConfig, BatteryKind};
+use crate::fips;
+use crate::profile::{Profile, TestProfile};
+use crate::report::Thresholds;
+use crate::tests::ParamValue;
+
+#[derive(Clone, Debug)]
+pub struct Preset {
+    pub name: String,
+    pub builtin: bool,
+    pub profile: Profile,
+}
+
+/// Default settings of tests with the parameters `(test, parameter, value)` changed.
+fn with_params(tests: &[TestProfile], params: &[(&str, &str, usize)]) -> Vec<TestProfile> {
+    tests
+        .iter()
+        .cloned()
+        .map(|mut test| {
+            params
+                .iter()
+                .filter(|(name, _, _)| *name == test.name)
+                .for_each(|(_, param, value)| {
+                    test.params
+                        .insert(param.to_string(), ParamValue::Int(*value));
+                });
+            test
+        })
+        .collect()
+}
+
+fn builtin(name: &str, n_bits: Option<usize>, tests: Vec<TestProfile>) -> Preset {
+    Preset {
+        name: name.to_string(),
+        builtin: true,
+        profile: Profile {
+            battery: Some(BatteryKind::Nist),
+            n_bits,
+            n_blocks: None,
+            thresholds: Some(Thresholds::default()),
+            tests,
+            ..Default::default()
+        },
+    }
+}
+
+/// Presets shipped with the program.
+pub fn builtin_presets() -> Vec<Preset> {
+    let nist = Profile::from_config(&BatteryConfig::new(0, 0));
+
+    vec![
+        // Parameters of the NIST STS reference implementation.
+        builtin("SP 800-22 defaults", Some(1_000_000), nist.tests.clone()),
+        // The template and linear complexity tests take most of the time.
+        builtin(
+            "Smoke",
+            None,
+            nist.tests
+                .iter()
+                .map(|t| TestProfile {
+                    name: t.name.clone(),
+                    enable: !matches!(
+                        t.name.as_str(),
+                        "NonOverlappingTemplate" | "Overlapping" | "LinearComplexity"
+                    ),
+                    params: Default::default(),
+                })
+                .collect(),
+        ),
+        // SP 800-22 recommendations for n = 10^7: M > 0.01n for BlockFrequency,
+        // N >= 200 blocks for LinearComplexity, m < log2(n) - 5 for ApproximateEntropy.
+        builtin(
+            "Long sequences (10^7 bits)",
+            Some(10_000_000),
+            with_params(
+                &nist.tests,
+                &[
+                    ("BlockFrequency", "M", 200_000),
+                    ("LinearComplexity", "M", 5_000),
+                    ("ApproximateEntropy", "m", 14),
+                ],
+            ),
+        ),
+        Preset {
+            name: "FIPS 140-2".to_string(),
+            builtin: true,
+            profile: {
+                let fips = Profile::from_config(
+                    &BatteryConfig::new(fips::BLOCK_BITS, 0).with_kind(BatteryKind::Fips140_2),
+                );
+                Profile {
+                    battery: fips.battery,
+                    n_bits: fips.n_bits,
+                    thresholds: fips.thresholds,
+                    tests: fips.tests,
+                    ..Default::default()
+                }
+            },
+        },
+    ]
+}
+
+/// Directory of user presets: `%APPDATA%\GuiNistRs\presets` on Windows,
+/// `$XDG_CONFIG_HOME/guinistrs/presets` or `~/.config/guinistrs/presets` elsewhere.
+pub fn presets_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        std::env::var_os("APPDATA").map(|v| PathBuf::from(v).join("GuiNistRs").join("presets"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|v| PathBuf::from(v).join(".config")))
+            .map(|v| v.join("guinistrs").join("presets"))
+    }
+}
+
+/// Presets saved by the user, files which can't be read are skipped.
+pub fn user_presets() -> Vec<Preset> {
+    let dir = match presets_dir().and_then(|dir| fs::read_dir(dir).ok()) {
+        Some(v) => v,
+        None => return vec![],
+    };
+
+    let mut presets: Vec<Preset> = dir
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "json" {
+                return None;
+            }
+
+            Some(Preset {
+                name: path.file_stem()?.to_string_lossy().into_owned(),
+                builtin: false,
+                profile: Profile::load(&path).ok()?,
+            })
+        })
+        .collect();
+
+    presets.sort_by(|a, b| a.name.cmp(&b.name));
+    presets
+}
+
+/// Built-in presets followed by the user ones.
+pub fn all_presets() -> Vec<Preset> {
+    let mut presets = builtin_presets();
+    presets.append(&mut user_presets());
+    presets
+}
+
+pub fn find_preset(name: &str) -> Option<Preset> {
+    all_presets()
+        .into_iter()
+        .find(|preset| preset.name.eq_ignore_ascii_case(name))
+}
+
+/// Saves `profile` as the user preset `name` and returns the path of the file.
+pub fn save_user_preset(name: &str, profile: &Profile) -> io::Result<PathBuf> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidInput, msg.to_string());
+
+    if name.trim().is_empty() {
+        return Err(invalid("Name of the preset is empty"));
+    }
+    if name.contains(['/', '\\', ':', '*', '?', '"', '<', '>', '|']) {
+        return Err(invalid("Name of the preset contains invalid characters"));
+    }
+    if builtin_presets()
+        .iter()
+        .any(|preset| preset.name.eq_ignore_ascii_case(name.trim()))
+    {
+        return Err(invalid("Name of the preset is used by a built-in preset"));
+    }
+
+    let dir = presets_dir().ok_or_else(|| invalid("Directory of presets is unknown"))?;
+    fs::create_dir_all(&dir)?;
+
+    let path = dir.join(format!("{}.json", name.trim()));
+    profile.save(&path)?;
+    Ok(path)
+}
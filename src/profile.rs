@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::battery::{BatteryConfig, BatteryKind};
+use crate::health::HealthConfig;
+use crate::input::{self, BitOrder, InputFormat, Region};
+use crate::report::Thresholds;
+use crate::tests::{NistWrapper, ParamValue};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TestProfile {
+    pub name: String,
+    pub enable: bool,
+    /// Values of parameters by name, missing parameters keep their values.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, ParamValue>,
+}
+
+/// Settings of a run which can be saved to a JSON file and shared between the GUI and the CLI.
+/// Settings missing in the file keep their current values.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    /// `None` keeps the current battery.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub battery: Option<BatteryKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n_bits: Option<usize>,
+    /// `None` keeps the number of blocks, e.g. calculated from the size of the file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n_blocks: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thresholds: Option<Thresholds>,
+    /// Health tests, `None` keeps the current setting.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health: Option<HealthConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<InputFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bit_order: Option<BitOrder>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub word_bits: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<Region>,
+    /// Tests missing in the profile keep their settings.
+    pub tests: Vec<TestProfile>,
+}
+
+impl Profile {
+    pub fn from_config(config: &BatteryConfig) -> Self {
+        Self {
+            battery: Some(config.kind),
+            n_bits: Some(config.n_bits),
+            n_blocks: Some(config.n_blocks),
+            thresholds: Some(config.thresholds),
+            health: config.health,
+            format: Some(config.format),
+            bit_order: Some(config.bit_order),
+            word_bits: Some(config.word_bits),
+            region: Some(config.region),
+            tests: config
+                .tests
+                .iter()
+                .map(|test| TestProfile {
+                    name: test.name.to_string(),
+                    enable: test.enable,
+                    params: test
+                        .params
+                        .iter()
+                        .map(|p| (p.spec.name.to_string(), p.value))
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Applies the settings of tests to `tests`, integer parameters are clamped to their ranges.
+    pub fn apply_tests(&self, tests: &mut [NistWrapper]) -> Result<(), String> {
+        for test_profile in &self.tests {
+            let test = tests
+                .iter_mut()
+                .find(|test| test.name.eq_ignore_ascii_case(&test_profile.name))
+                .ok_or(format!("Unknown test: {}", test_profile.name))?;
+
+            test.enable = test_profile.enable;
+
+            for (name, value) in &test_profile.params {
+                let test_name = test.name;
+                let param = test
+                    .param_mut(name)
+                    .ok_or(format!("Unknown parameter {} of {}", name, test_name))?;
+                param.value = param.spec.check(*value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Switching the battery resets its tests and block size to the defaults of the battery
+    /// before applying the other settings.
+    pub fn apply(&self, config: &mut BatteryConfig) -> Result<(), String> {
+        if let Some(kind) = self.battery.filter(|kind| *kind != config.kind) {
+            config.kind = kind;
+            config.tests = kind.tests();
+            config.n_bits = kind.default_bits();
+        }
+        self.apply_tests(&mut config.tests)?;
+        config.n_bits = self.n_bits.unwrap_or(config.n_bits);
+        config.n_blocks = self.n_blocks.unwrap_or(config.n_blocks);
+        config.thresholds = self.thresholds.unwrap_or(config.thresholds);
+        config.health = self.health.or(config.health);
+        if let Some(v) = self.word_bits {
+            input::check_word_bits(v)?;
+            config.word_bits = v;
+        }
+        config.format = self.format.unwrap_or(config.format);
+        config.bit_order = self.bit_order.unwrap_or(config.bit_order);
+        config.region = self.region.unwrap_or(config.region);
+
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let data = fs::read_to_string(path)?;
+        serde_json::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let data = serde_json::to_string_pretty(self).expect("Profile is always serializable");
+        fs::write(path, data)
+    }
+}
@@ -0,0 +1,816 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as FmtWrite;
+
+use nistrs::TEST_THRESHOLD;
+use statrs::function::gamma::gamma_ur;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ais31;
+use crate::battery::BatteryKind;
+use crate::error::TestError;
+use crate::health::{HealthReport, HealthTest};
+use crate::input::{BitOrder, InputFormat, Region};
+use crate::tests::{FailedBlock, NistWrapper, ParamValue, ResultTestsStat};
+
+/// Pass criteria of the battery.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Thresholds {
+    /// Significance level of a single test: a sequence passes if P-value >= `alpha`.
+    pub alpha: f64,
+    /// Minimum P-value of the uniformity test of P-values.
+    pub uniformity: f64,
+    /// Width of the acceptable range of the proportion in standard deviations.
+    pub proportion_sigma: f64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            alpha: TEST_THRESHOLD,
+            uniformity: 0.0001,
+            proportion_sigma: 3_f64,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TestInfo {
+    pub name: &'static str,
+    pub params: BTreeMap<&'static str, ParamValue>,
+}
+
+/// Settings the report was built with.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RunInfo {
+    pub version: String,
+    pub battery: BatteryKind,
+    pub input: String,
+    /// Format of the input, detected if it was set to auto.
+    pub format: InputFormat,
+    pub bit_order: BitOrder,
+    /// Bytes of words of this size were swapped, 8 - no swapping.
+    pub word_bits: usize,
+    pub region: Region,
+    /// Start of the run, seconds since the Unix epoch.
+    pub started_at: u64,
+    pub duration_ms: u64,
+    pub n_bits: usize,
+    /// Requested number of blocks.
+    pub n_blocks: usize,
+    /// Number of complete blocks read, less than `n_blocks` if the input ended.
+    pub blocks_read: usize,
+    /// Number of bits read, including an incomplete last block.
+    pub bits_read: u64,
+    pub thresholds: Thresholds,
+    /// Enabled tests.
+    pub tests: Vec<TestInfo>,
+}
+
+/// Second-level statistics of one test result (a test can return several P-values).
+#[derive(Clone, Debug, Serialize)]
+pub struct ReportRow {
+    #[serde(rename = "test")]
+    pub name: &'static str,
+    /// Label of the result for tests with several results, e.g. the template of NonOverlappingTemplate.
+    pub label: String,
+    #[serde(rename = "histogram")]
+    pub p_distr: [usize; 10],
+    /// P-value of the chi-square uniformity test of P-values.
+    #[serde(rename = "p_value")]
+    pub uniformity: f64,
+    /// Proportion of passed sequences.
+    pub proportion: f64,
+    /// Acceptable range of the proportion for the sample size of this result.
+    pub proportion_bounds: (f64, f64),
+    pub passed: bool,
+    /// The test returned a result for some blocks. A test which failed or wasn't applicable
+    /// on every block isn't evaluated, it's neither passed nor counted as failed.
+    pub evaluated: bool,
+    pub samples: usize,
+    pub errors: usize,
+    pub not_applicable: usize,
+    /// Blocks which failed the test.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed_blocks: Vec<FailedBlock>,
+}
+
+impl ReportRow {
+    /// Name of the test with the label of the result.
+    pub fn full_name(&self) -> String {
+        if self.label.is_empty() {
+            self.name.to_string()
+        } else {
+            format!("{} {}", self.name, self.label)
+        }
+    }
+
+    /// Mark of the row in the text report.
+    fn mark(&self) -> &'static str {
+        if self.passed || !self.evaluated {
+            " "
+        } else {
+            "*"
+        }
+    }
+
+    /// Name of the row in the text report.
+    fn text_name(&self) -> String {
+        if self.evaluated {
+            self.full_name()
+        } else {
+            format!("{} (not evaluated)", self.full_name())
+        }
+    }
+}
+
+/// Min-entropy estimate of one estimator, in bits per sample (per bit for bitstrings).
+#[derive(Clone, Debug, Serialize)]
+pub struct Estimate {
+    pub name: String,
+    /// The lowest estimate of all blocks.
+    pub min_entropy: f64,
+    /// Estimates of every block.
+    pub values: Vec<f64>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Report {
+    pub info: RunInfo,
+    pub rows: Vec<ReportRow>,
+    #[serde(skip)]
+    pub text: String,
+    #[serde(skip)]
+    pub p_distr: [usize; 10],
+    #[serde(skip)]
+    pub p_p_distr: Vec<f64>,
+    pub failed: usize,
+    /// Number of rows which weren't evaluated or had errors, the run didn't test everything.
+    pub incomplete: usize,
+    /// Results of the SP 800-90B estimators.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub estimates: Vec<Estimate>,
+    /// Final min-entropy per sample of the SP 800-90B assessment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub h_min: Option<f64>,
+    /// Results of the SP 800-90B health tests run over the same input.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health: Option<HealthReport>,
+    /// Errors of single tests, they reduce the sample size of the test.
+    pub errors: Vec<TestError>,
+}
+
+pub fn calc_stat(
+    tests: &[NistWrapper],
+    stat: ResultTestsStat,
+    errors: Vec<TestError>,
+    info: RunInfo,
+) -> Report {
+    calc_p_value_stat(tests, stat, errors, info, true)
+}
+
+/// Report of GM/T 0005-2021: the pass rate is only checked against the lower bound.
+pub fn calc_gmt_stat(
+    tests: &[NistWrapper],
+    stat: ResultTestsStat,
+    errors: Vec<TestError>,
+    info: RunInfo,
+) -> Report {
+    calc_p_value_stat(tests, stat, errors, info, false)
+}
+
+/// Second-level statistics of P-values, `two_sided` - the proportion also fails above the range.
+fn calc_p_value_stat(
+    tests: &[NistWrapper],
+    stat: ResultTestsStat,
+    errors: Vec<TestError>,
+    info: RunInfo,
+    two_sided: bool,
+) -> Report {
+    let thresholds = info.thresholds;
+    let mut report = Report {
+        text: String::with_capacity(18700),
+        errors,
+        ..Default::default()
+    };
+
+    write_input(&mut report, &info);
+    if info.battery != BatteryKind::Nist {
+        writeln!(report.text, "Battery: {}", info.battery.name()).unwrap();
+    }
+    write_blocks(&mut report, &info);
+    writeln!(report.text, "Significance level: {}", thresholds.alpha).unwrap();
+    writeln!(
+        report.text,
+        "Uniformity threshold: {}",
+        thresholds.uniformity
+    )
+    .unwrap();
+    writeln!(
+        report.text,
+        "Proportion range: {} sigma\n",
+        thresholds.proportion_sigma
+    )
+    .unwrap();
+
+    report.text += &(1..=10)
+        .map(|i| format!("{:>5}", format!("C{}", i)))
+        .collect::<String>();
+    report.text += &format!(
+        "{:>12}{:>12}{:>9}{:>8}{:>8}   STATISTICAL TEST\n",
+        "P-VALUE", "PROPORTION", "SAMPLES", "ERRORS", "N/A"
+    );
+
+    tests.iter().zip(stat).for_each(|(test, st)| {
+        if !test.enable {
+            return;
+        }
+
+        let pa = 1_f64 - thresholds.alpha;
+
+        let labels = test.labels();
+
+        for (k, test_st) in st.into_iter().enumerate() {
+            // Without results the number of subtests is unknown, a single row stands for the test.
+            let evaluated = test_st.samples > 0;
+            let label = if evaluated {
+                labels.get(k).cloned().unwrap_or_default()
+            } else {
+                String::new()
+            };
+
+            // Only the blocks the test was applicable to are counted,
+            // so the range differs e.g. for RandomExcursions (SP 800-22, 4.2.1).
+            let sample_size = test_st.samples as f64;
+            let p_range = thresholds.proportion_sigma * (pa * (1_f64 - pa) / sample_size).sqrt();
+            let min_p = pa - p_range;
+            let max_p = if two_sided { pa + p_range } else { 1_f64 };
+
+            let c_tmp = (sample_size / 10_f64).floor() as isize;
+
+            let mut chi_squad = f64::default();
+            report
+                .p_distr
+                .iter_mut()
+                .zip(test_st.p_distr)
+                .for_each(|(out, p)| {
+                    report.text += &format!("{:>5}", p);
+                    *out += p;
+
+                    chi_squad += (p as isize - c_tmp).pow(2) as f64;
+                });
+
+            chi_squad /= c_tmp as f64;
+            if chi_squad > 0_f64 && !chi_squad.is_infinite() {
+                chi_squad = gamma_ur(9.0 / 2.0, chi_squad / 2.0);
+            } else {
+                chi_squad = 0_f64;
+            }
+
+            let ratio = if test_st.samples > 0 {
+                test_st.ratio / sample_size
+            } else {
+                0_f64
+            };
+
+            let is_rand =
+                evaluated && ratio >= min_p && ratio <= max_p && chi_squad >= thresholds.uniformity;
+            if evaluated && !is_rand {
+                report.failed += 1;
+            }
+            if !evaluated || test_st.errors > 0 {
+                report.incomplete += 1;
+            }
+
+            report.text += &format!(
+                "{:>12.5}{:>12.5}{:>9}{:>8}{:>8}",
+                chi_squad, ratio, test_st.samples, test_st.errors, test_st.not_applicable
+            );
+
+            let row = ReportRow {
+                name: test.name,
+                label,
+                p_distr: test_st.p_distr,
+                uniformity: chi_squad,
+                proportion: ratio,
+                proportion_bounds: (min_p, max_p),
+                passed: is_rand,
+                evaluated,
+                samples: test_st.samples,
+                errors: test_st.errors,
+                not_applicable: test_st.not_applicable,
+                failed_blocks: test_st.failed_blocks,
+            };
+
+            writeln!(report.text, " {} {}", row.mark(), row.text_name()).unwrap();
+
+            report.p_p_distr.push(ratio);
+            report.rows.push(row);
+        }
+    });
+
+    report.info = info;
+    write_footer(&mut report, "Number of failed tests (*)", "");
+
+    report
+}
+
+fn write_input(report: &mut Report, info: &RunInfo) {
+    if !info.input.is_empty() {
+        writeln!(report.text, "Input: {}", info.input).unwrap();
+    }
+    if info.format != InputFormat::Raw {
+        writeln!(report.text, "Format: {}", info.format.name()).unwrap();
+    }
+    if info.bit_order != BitOrder::Msb || info.word_bits > u8::BITS as usize {
+        writeln!(
+            report.text,
+            "Unpacking: {}, {}-bit words",
+            info.bit_order.name(),
+            info.word_bits
+        )
+        .unwrap();
+    }
+    if !info.region.is_whole() {
+        let length = info
+            .region
+            .length
+            .map_or("to the end".to_string(), |v| format!("{} bits", v));
+        writeln!(
+            report.text,
+            "Region: offset {} bits, length {}, skip {} bits after each block",
+            info.region.offset, length, info.region.skip
+        )
+        .unwrap();
+    }
+}
+
+fn write_blocks(report: &mut Report, info: &RunInfo) {
+    writeln!(
+        report.text,
+        "Blocks: {} of {} bits",
+        info.blocks_read, info.n_bits
+    )
+    .unwrap();
+    if info.blocks_read < info.n_blocks {
+        writeln!(
+            report.text,
+            "Input ended: {} of {} requested bits read, {} of {} blocks tested",
+            info.bits_read,
+            info.n_blocks as u64 * info.n_bits as u64,
+            info.blocks_read,
+            info.n_blocks
+        )
+        .unwrap();
+    }
+}
+
+/// `summary` with the number of failed tests, `details` of failures and errors.
+fn write_footer(report: &mut Report, summary: &str, details: &str) {
+    report.text += "\n";
+    write!(report.text, "{}: {}", summary, report.failed).unwrap();
+    if report.incomplete > 0 {
+        write!(
+            report.text,
+            "\nTests not evaluated or with errors: {}",
+            report.incomplete
+        )
+        .unwrap();
+    }
+    report.text += details;
+    write_errors(report);
+}
+
+fn write_errors(report: &mut Report) {
+    if !report.errors.is_empty() {
+        write!(report.text, "\n\nErrors ({}):", report.errors.len()).unwrap();
+        report.errors.iter().for_each(|e| {
+            write!(report.text, "\n{}", e).unwrap();
+        });
+    }
+}
+
+fn write_pass_header(report: &mut Report, info: &RunInfo) {
+    write_input(report, info);
+    writeln!(report.text, "Battery: {}", info.battery.name()).unwrap();
+    write_blocks(report, info);
+    report.text += "\n";
+
+    report.text += &format!(
+        "{:>8}{:>9}{:>8}{:>8}   STATISTICAL TEST\n",
+        "PASSED", "SAMPLES", "ERRORS", "N/A"
+    );
+}
+
+fn format_value(value: f64) -> String {
+    if value.fract() == 0_f64 {
+        format!("{}", value)
+    } else {
+        format!("{:.5}", value)
+    }
+}
+
+/// Adds a row per result which passes if it passes on every block. Returns the list of failed blocks.
+fn add_pass_rows(report: &mut Report, tests: &[NistWrapper], stat: ResultTestsStat) -> String {
+    const MAX_LISTED_BLOCKS: usize = 100;
+
+    let mut failed_text = String::new();
+    tests
+        .iter()
+        .zip(stat)
+        .filter(|(test, _)| test.enable)
+        .for_each(|(test, st)| {
+            let labels = test.labels();
+
+            for (k, test_st) in st.into_iter().enumerate() {
+                let evaluated = test_st.samples > 0;
+                let row = ReportRow {
+                    name: test.name,
+                    label: if evaluated {
+                        labels.get(k).cloned().unwrap_or_default()
+                    } else {
+                        String::new()
+                    },
+                    p_distr: [0; 10],
+                    uniformity: f64::NAN,
+                    proportion: if test_st.samples > 0 {
+                        test_st.ratio / test_st.samples as f64
+                    } else {
+                        0_f64
+                    },
+                    proportion_bounds: (1_f64, 1_f64),
+                    passed: evaluated && test_st.failed_blocks.is_empty(),
+                    evaluated,
+                    samples: test_st.samples,
+                    errors: test_st.errors,
+                    not_applicable: test_st.not_applicable,
+                    failed_blocks: test_st.failed_blocks,
+                };
+
+                if evaluated && !row.passed {
+                    report.failed += 1;
+                }
+                if !evaluated || row.errors > 0 {
+                    report.incomplete += 1;
+                }
+
+                report.text += &format!(
+                    "{:>8}{:>9}{:>8}{:>8} {} {}\n",
+                    test_st.ratio as usize,
+                    row.samples,
+                    row.errors,
+                    row.not_applicable,
+                    row.mark(),
+                    row.text_name()
+                );
+
+                if !row.failed_blocks.is_empty() {
+                    let listed: Vec<String> = row
+                        .failed_blocks
+                        .iter()
+                        .take(MAX_LISTED_BLOCKS)
+                        .map(|v| format!("{} ({})", v.block, format_value(v.value)))
+                        .collect();
+                    write!(failed_text, "\n{}: {}", row.full_name(), listed.join(", ")).unwrap();
+                    if row.failed_blocks.len() > MAX_LISTED_BLOCKS {
+                        write!(
+                            failed_text,
+                            " and {} more",
+                            row.failed_blocks.len() - MAX_LISTED_BLOCKS
+                        )
+                        .unwrap();
+                    }
+                }
+
+                report.p_p_distr.push(row.proportion);
+                report.rows.push(row);
+            }
+        });
+
+    if !failed_text.is_empty() {
+        failed_text.insert_str(0, "\n\nFailed blocks (value):");
+    }
+
+    failed_text
+}
+
+/// Report of a battery with fixed pass intervals: a test passes if it passes on every block.
+pub fn calc_pass_stat(
+    tests: &[NistWrapper],
+    stat: ResultTestsStat,
+    errors: Vec<TestError>,
+    info: RunInfo,
+) -> Report {
+    let mut report = Report {
+        errors,
+        ..Default::default()
+    };
+
+    write_pass_header(&mut report, &info);
+    let failed_text = add_pass_rows(&mut report, tests, stat);
+
+    report.info = info;
+    write_footer(&mut report, "Number of failed tests (*)", &failed_text);
+
+    report
+}
+
+/// Report of the AIS 31 procedures: blocks are consecutive applications of each procedure,
+/// a procedure with a single failed test is repeated on the next block.
+pub fn calc_ais_stat(
+    tests: &[NistWrapper],
+    stat: ResultTestsStat,
+    errors: Vec<TestError>,
+    info: RunInfo,
+) -> Report {
+    let mut report = Report {
+        errors,
+        ..Default::default()
+    };
+
+    write_pass_header(&mut report, &info);
+    let failed_text = add_pass_rows(&mut report, tests, stat);
+
+    report.failed = 0;
+    report.text += "\nAIS 31 procedures:\n";
+    for test in tests.iter().filter(|test| test.enable) {
+        // Blocks after the end of the input aren't applications of the procedure.
+        let mut failures = vec![0_usize; info.blocks_read];
+
+        report
+            .rows
+            .iter()
+            .filter(|row| row.name == test.name)
+            .flat_map(|row| {
+                row.failed_blocks
+                    .iter()
+                    .map(|v| (v.block, ais31::failures(&row.label, v.value)))
+            })
+            .for_each(|(block, n)| failures[block] += n);
+
+        // A procedure which couldn't run on a block fails on it.
+        report
+            .errors
+            .iter()
+            .filter(|e| e.test() == Some(test.name))
+            .filter_map(TestError::block)
+            .for_each(|block| failures[block] = usize::MAX);
+
+        let verdict = ais31::verdict(&failures);
+        let passed = verdict.failed == 0 && verdict.passed + verdict.repeated > 0;
+        if !passed {
+            report.failed += 1;
+        }
+
+        writeln!(
+            report.text,
+            "{} {}: passed {}, passed after repetition {}, failed {}",
+            if passed { " " } else { "*" },
+            test.name,
+            verdict.passed,
+            verdict.repeated,
+            verdict.failed
+        )
+        .unwrap();
+    }
+
+    report.info = info;
+    write_footer(&mut report, "Number of failed procedures (*)", &failed_text);
+
+    report
+}
+
+/// Report of the SP 800-90B estimators, the estimate of each estimator is the lowest of all blocks.
+pub fn calc_entropy_stat(
+    tests: &[NistWrapper],
+    stat: ResultTestsStat,
+    errors: Vec<TestError>,
+    info: RunInfo,
+) -> Report {
+    let mut report = Report {
+        errors,
+        ..Default::default()
+    };
+
+    write_input(&mut report, &info);
+    writeln!(report.text, "Battery: {}", info.battery.name()).unwrap();
+    write_blocks(&mut report, &info);
+
+    for (test, st) in tests.iter().zip(stat).filter(|(test, _)| test.enable) {
+        let width = test.param("Sample bits").map_or(8, |p| p.int());
+        let track = match test.param("IID").map(|p| p.value) {
+            Some(ParamValue::Bool(true)) => "IID",
+            _ => "non-IID",
+        };
+        writeln!(
+            report.text,
+            "Samples: {} bits in each byte, {} track\n",
+            width, track
+        )
+        .unwrap();
+        report.text += &format!("{:>12}   ESTIMATOR\n", "H_MIN");
+
+        let labels = test.labels();
+        for (k, test_st) in st.into_iter().enumerate() {
+            let estimate = Estimate {
+                name: labels.get(k).cloned().unwrap_or_default(),
+                min_entropy: test_st.values.iter().copied().fold(f64::NAN, f64::min),
+                values: test_st.values,
+            };
+
+            if k + 1 == labels.len() {
+                report.h_min = Some(estimate.min_entropy);
+            } else {
+                writeln!(
+                    report.text,
+                    "{:>12.6}   {}",
+                    estimate.min_entropy, estimate.name
+                )
+                .unwrap();
+                report.p_p_distr.push(estimate.min_entropy);
+            }
+            report.estimates.push(estimate);
+        }
+    }
+
+    report.info = info;
+    report.text += "\n";
+    match report.h_min {
+        Some(h) if !h.is_nan() => write!(report.text, "H_min: {:.6} bits per sample", h).unwrap(),
+        _ => report.text += "H_min: not estimated",
+    }
+    write_errors(&mut report);
+
+    report
+}
+
+/// Appends the results of the health tests to the report.
+pub fn add_health(report: &mut Report, health: HealthReport) {
+    const MAX_LISTED_VIOLATIONS: usize = 100;
+
+    write!(
+        report.text,
+        "\n\nHealth tests (SP 800-90B, 4.4), claimed entropy {} of {} bits per sample, \
+         {} samples (bytes 0..{} up to the end of the last block):",
+        health.config.entropy, health.config.sample_bits, health.samples, health.samples
+    )
+    .unwrap();
+    for (test, cutoff) in [
+        (
+            HealthTest::RepetitionCount,
+            format!("cutoff {}", health.rct_cutoff),
+        ),
+        (
+            HealthTest::AdaptiveProportion,
+            format!("window {}, cutoff {}", health.apt_window, health.apt_cutoff),
+        ),
+    ] {
+        let count = health.count(test);
+        write!(
+            report.text,
+            "\n{} {}: {}, violations {}",
+            if count == 0 { " " } else { "*" },
+            test.name(),
+            cutoff,
+            count
+        )
+        .unwrap();
+    }
+
+    if !health.passed() {
+        let listed: Vec<String> = health
+            .violations
+            .iter()
+            .take(MAX_LISTED_VIOLATIONS)
+            .map(|v| format!("{} {} ({})", v.test.name(), v.offset, v.sample))
+            .collect();
+        let offset = if report.info.format == InputFormat::Raw {
+            "byte offset in the input"
+        } else {
+            "byte offset in the decoded input"
+        };
+        write!(
+            report.text,
+            "\nViolations ({}, sample): {}",
+            offset,
+            listed.join(", ")
+        )
+        .unwrap();
+        if health.violations.len() > MAX_LISTED_VIOLATIONS {
+            write!(
+                report.text,
+                " and {} more",
+                health.violations.len() - MAX_LISTED_VIOLATIONS
+            )
+            .unwrap();
+        }
+    }
+
+    report.health = Some(health);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fips;
+    use crate::tests::{self, TestStat};
+
+    /// Statistics of the AIS 31 procedures on `blocks` blocks with `failed` blocks of T1.
+    fn ais_stat(blocks: usize, failed: &[(usize, f64)]) -> ResultTestsStat {
+        let passed = |n_labels| {
+            vec![
+                TestStat {
+                    ratio: blocks as f64,
+                    samples: blocks,
+                    ..Default::default()
+                };
+                n_labels
+            ]
+        };
+        let mut procedure_a = passed(6);
+        procedure_a[1].ratio -= failed.len() as f64;
+        procedure_a[1].failed_blocks = failed
+            .iter()
+            .map(|(block, value)| FailedBlock {
+                block: *block,
+                value: *value,
+            })
+            .collect();
+        vec![procedure_a, passed(5)]
+    }
+
+    fn ais_info(n_blocks: usize, blocks_read: usize) -> RunInfo {
+        RunInfo {
+            battery: BatteryKind::Ais31,
+            n_bits: ais31::PROCEDURE_A_BITS,
+            n_blocks,
+            blocks_read,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ais_counts_read_blocks() {
+        let tests = ais31::ais31_tests();
+        let report = calc_ais_stat(&tests, ais_stat(1, &[]), vec![], ais_info(5, 1));
+        assert!(report
+            .text
+            .contains("ProcedureA: passed 1, passed after repetition 0, failed 0"));
+        assert!(report
+            .text
+            .contains("ProcedureB: passed 1, passed after repetition 0, failed 0"));
+        assert_eq!(report.failed, 0);
+    }
+
+    #[test]
+    fn ais_repeats_single_failure() {
+        let tests = ais31::ais31_tests();
+        // A single failed sequence of T1 on block 0 is repeated on block 1.
+        let report = calc_ais_stat(&tests, ais_stat(3, &[(0, 1_f64)]), vec![], ais_info(3, 3));
+        assert!(report
+            .text
+            .contains("ProcedureA: passed 1, passed after repetition 1, failed 0"));
+        assert_eq!(report.failed, 0);
+
+        // The input ended before the repetition.
+        let report = calc_ais_stat(&tests, ais_stat(1, &[(0, 1_f64)]), vec![], ais_info(2, 1));
+        assert!(report
+            .text
+            .contains("ProcedureA: passed 0, passed after repetition 0, failed 1"));
+        assert_eq!(report.failed, 1);
+    }
+
+    #[test]
+    fn counts_not_evaluated_tests() {
+        let tests = fips::fips_tests();
+        let stat = vec![vec![TestStat::default()]; tests.len()];
+        let info = RunInfo {
+            battery: BatteryKind::Fips140_2,
+            ..Default::default()
+        };
+        let report = calc_pass_stat(&tests, stat, vec![], info);
+        assert_eq!(report.failed, 0);
+        assert_eq!(report.incomplete, 4);
+        assert!(report.rows.iter().all(|row| !row.evaluated && !row.passed));
+        assert!(report
+            .text
+            .contains("Tests not evaluated or with errors: 4"));
+    }
+
+    #[test]
+    fn counts_tests_with_errors() {
+        let tests = tests::nist_tests();
+        let mut stat = vec![vec![]; tests.len()];
+        stat[0] = vec![TestStat {
+            ratio: 10_f64,
+            p_distr: [2, 0, 1, 1, 1, 1, 1, 1, 1, 1],
+            samples: 10,
+            errors: 1,
+            ..Default::default()
+        }];
+        let report = calc_stat(&tests, stat, vec![], RunInfo::default());
+        assert_eq!(report.failed, 0);
+        assert_eq!(report.incomplete, 1);
+    }
+}
@@ -0,0 +1,119 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::TestError;
+use crate::report::Report;
+
+/// Cancellation token and progress counters of one run.
+#[derive(Debug, Default)]
+pub struct Progress {
+    stop: AtomicBool,
+    n_blocks: usize,
+    complete_blocks: AtomicUsize,
+    avr_time_to_block: AtomicU64,
+    total_time: AtomicU64,
+}
+
+impl Progress {
+    pub fn new(n_blocks: usize) -> Self {
+        Self {
+            n_blocks,
+            ..Default::default()
+        }
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    pub fn n_blocks(&self) -> usize {
+        self.n_blocks
+    }
+
+    pub fn complete_blocks(&self) -> usize {
+        self.complete_blocks.load(Ordering::Relaxed)
+    }
+
+    pub fn avr_time_to_block(&self) -> Duration {
+        Duration::from_millis(self.avr_time_to_block.load(Ordering::Relaxed))
+    }
+
+    pub fn total_time(&self) -> Duration {
+        Duration::from_millis(self.total_time.load(Ordering::Relaxed))
+    }
+
+    pub fn time_left(&self) -> Duration {
+        let blocks_left = self.n_blocks.saturating_sub(self.complete_blocks()) as u32;
+        self.avr_time_to_block() * blocks_left
+    }
+
+    pub(crate) fn block_done(&self, avr_time_to_block: Duration, total_time: Duration) {
+        self.complete_blocks.fetch_add(1, Ordering::Relaxed);
+        self.avr_time_to_block
+            .store(avr_time_to_block.as_millis() as u64, Ordering::Relaxed);
+        self.total_time
+            .store(total_time.as_millis() as u64, Ordering::Relaxed);
+    }
+}
+
+/// `Ok(None)` - the run was stopped.
+pub type RunResult = Result<Option<Report>, TestError>;
+
+/// Message sent by the worker thread.
+#[derive(Debug)]
+pub enum RunEvent {
+    /// A test failed on some block, the run goes on.
+    Error(TestError),
+    /// The run is over, this is the last message.
+    Finished(Box<RunResult>),
+}
+
+/// Handle of a run started with [`crate::start_thread`].
+pub struct RunHandle {
+    progress: Arc<Progress>,
+    receiver: Receiver<RunEvent>,
+}
+
+impl RunHandle {
+    pub(crate) fn new(progress: Arc<Progress>, receiver: Receiver<RunEvent>) -> Self {
+        Self { progress, receiver }
+    }
+
+    pub fn progress(&self) -> &Arc<Progress> {
+        &self.progress
+    }
+
+    /// Requests the run to stop; the result will be `Ok(None)`.
+    pub fn stop(&self) {
+        self.progress.stop();
+    }
+
+    /// Waits up to `timeout` for the next message from the worker thread.
+    /// Returns `None` if there is no message yet.
+    pub fn poll(&self, timeout: Duration) -> Option<RunEvent> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(v) => Some(v),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => {
+                Some(RunEvent::Finished(Box::new(Err(TestError::Disconnected))))
+            }
+        }
+    }
+
+    /// Blocks until the run is finished. Errors of single tests are available in the report.
+    pub fn join(self) -> RunResult {
+        loop {
+            match self.receiver.recv() {
+                Ok(RunEvent::Error(_)) => {}
+                Ok(RunEvent::Finished(v)) => return *v,
+                Err(_) => return Err(TestError::Disconnected),
+            }
+        }
+    }
+}
@@ -1,180 +1,456 @@
 use nistrs::prelude::*;
 
-use std::{sync::Mutex, time::Duration};
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone)]
+use std::sync::Arc;
+
+use crate::universal;
+
+/// Type and range of a parameter.
+#[derive(Clone, Copy, Debug)]
+pub enum ParamKind {
+    Int {
+        min: usize,
+        max: usize,
+    },
+    /// An integer in `min..=max` or 0 - chosen by the test.
+    IntOrAuto {
+        min: usize,
+        max: usize,
+    },
+    Bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ParamValue {
+    Bool(bool),
+    Int(usize),
+}
+
+/// Description of a parameter of a test.
+#[derive(Clone, Copy, Debug)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub kind: ParamKind,
+    pub default: ParamValue,
+}
+
+impl ParamSpec {
+    pub const fn int(
+        name: &'static str,
+        description: &'static str,
+        min: usize,
+        max: usize,
+        default: usize,
+    ) -> Self {
+        Self {
+            name,
+            description,
+            kind: ParamKind::Int { min, max },
+            default: ParamValue::Int(default),
+        }
+    }
+
+    /// An integer parameter in `min..=max` which is chosen by the test when it's 0, the default.
+    pub const fn int_or_auto(
+        name: &'static str,
+        description: &'static str,
+        min: usize,
+        max: usize,
+    ) -> Self {
+        Self {
+            name,
+            description,
+            kind: ParamKind::IntOrAuto { min, max },
+            default: ParamValue::Int(0),
+        }
+    }
+
+    /// Converts `value` to the type of the parameter, integers are clamped to the range.
+    pub fn check(&self, value: ParamValue) -> Result<ParamValue, String> {
+        match (self.kind, value) {
+            (ParamKind::IntOrAuto { .. }, ParamValue::Int(0)) => Ok(ParamValue::Int(0)),
+            (
+                ParamKind::Int { min, max } | ParamKind::IntOrAuto { min, max },
+                ParamValue::Int(v),
+            ) => Ok(ParamValue::Int(v.clamp(min, max))),
+            (ParamKind::Bool, ParamValue::Bool(v)) => Ok(ParamValue::Bool(v)),
+            _ => Err(format!("Invalid type of parameter {}", self.name)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
 pub struct TestParam {
-    pub _min_value: usize,
-    pub _max_value: usize,
-    pub value: usize,
+    pub spec: ParamSpec,
+    pub value: ParamValue,
+}
+
+impl TestParam {
+    pub fn new(spec: ParamSpec) -> Self {
+        Self {
+            spec,
+            value: spec.default,
+        }
+    }
+
+    pub fn int(&self) -> usize {
+        match self.value {
+            ParamValue::Int(v) => v,
+            ParamValue::Bool(v) => v as usize,
+        }
+    }
 }
 
-#[derive(Clone, Copy, Debug, Default)]
+/// A block which failed a test with the value returned by the test, a P-value or a statistic.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct FailedBlock {
+    pub block: usize,
+    pub value: f64,
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct TestStat {
     pub ratio: f64,
     pub p_distr: [usize; 10],
+    /// Number of blocks the P-value was calculated for.
+    pub samples: usize,
+    /// Number of blocks the test returned an error for.
+    pub errors: usize,
+    /// Number of blocks the test is not applicable to.
+    pub not_applicable: usize,
+    /// Blocks which failed the test.
+    pub failed_blocks: Vec<FailedBlock>,
+    /// Results of every block, kept for batteries which report the values.
+    pub values: Vec<f64>,
+}
+
+impl TestStat {
+    /// Statistics of a new subtest: the blocks skipped by the test are skipped by all its subtests.
+    pub fn skipped_like(&self) -> Self {
+        Self {
+            errors: self.errors,
+            not_applicable: self.not_applicable,
+            ..Default::default()
+        }
+    }
+}
+
+pub type ResultTestsStat = Vec<Vec<TestStat>>;
+pub type TestFn = fn(&BitsData, &[TestParam]) -> Result<Vec<TestResultT>, Option<String>>;
+/// Returns labels of the results returned by the test, empty for tests with a single result.
+pub type LabelsFn = fn(&[TestParam]) -> Vec<String>;
+
+/// A statistical test which can be added to the battery.
+pub trait RandomnessTest: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Parameters of the test, their values are passed to [`RandomnessTest::run`] in the same order.
+    fn params(&self) -> &[ParamSpec] {
+        &[]
+    }
+
+    /// Recommended minimum length of a sequence in bits.
+    fn min_length(&self, _params: &[TestParam]) -> usize {
+        0
+    }
+
+    /// Maximum length of a sequence in bits the test accepts.
+    fn max_length(&self, _params: &[TestParam]) -> usize {
+        usize::MAX
+    }
+
+    /// Returns P-values of the results, `Err(None)` if the test is not applicable to the sequence.
+    fn run(
+        &self,
+        data: &BitsData,
+        params: &[TestParam],
+    ) -> Result<Vec<TestResultT>, Option<String>>;
+
+    /// Labels of the results returned by the test, empty for tests with a single result.
+    fn labels(&self, _params: &[TestParam]) -> Vec<String> {
+        vec![]
+    }
+}
+
+/// A test made of functions, used for the NIST tests.
+pub struct FnTest {
+    pub name: &'static str,
+    pub params: Vec<ParamSpec>,
+    pub min_length: fn(&[TestParam]) -> usize,
+    pub run: TestFn,
+    pub labels: LabelsFn,
 }
 
-pub const NUMBER_OF_TEST: usize = 15;
+impl RandomnessTest for FnTest {
+    fn name(&self) -> &'static str {
+        self.name
+    }
 
-pub type ResultTestsStat = [Vec<TestStat>; NUMBER_OF_TEST];
-pub type TestFn = fn(&BitsData, Option<TestParam>) -> Result<Vec<TestResultT>, Option<String>>;
+    fn params(&self) -> &[ParamSpec] {
+        &self.params
+    }
 
-#[derive(Copy, Clone)]
+    fn min_length(&self, params: &[TestParam]) -> usize {
+        (self.min_length)(params)
+    }
+
+    fn run(
+        &self,
+        data: &BitsData,
+        params: &[TestParam],
+    ) -> Result<Vec<TestResultT>, Option<String>> {
+        (self.run)(data, params)
+    }
+
+    fn labels(&self, params: &[TestParam]) -> Vec<String> {
+        (self.labels)(params)
+    }
+}
+
+/// A registered test with its settings.
+#[derive(Clone)]
 pub struct NistWrapper {
     pub name: &'static str,
     pub enable: bool,
-    pub param: Option<TestParam>,
-    pub test_cb: TestFn,
-}
-
-lazy_static! {
-    pub static ref TESTS: Mutex<[NistWrapper; NUMBER_OF_TEST]> = {
-        Mutex::new([
-            NistWrapper {
-                name: "Frequency",
-                enable: true,
-                param: None,
-                test_cb: |data, _| Ok(vec![frequency_test(data)]),
-            },
-            NistWrapper {
-                name: "BlockFrequency",
-                enable: true,
-                param: Some(TestParam {
-                    _min_value: 10,
-                    _max_value: 1000,
-                    value: 128,
-                }),
-                test_cb: |data, param| match block_frequency_test(data, param.unwrap().value) {
-                    Ok(v) => Ok(vec![v]),
-                    Err(s) => Err(Some(s)),
-                },
-            },
-            NistWrapper {
-                name: "Runs",
-                enable: true,
-                param: None,
-                test_cb: |data, _| Ok(vec![runs_test(data)]),
-            },
-            NistWrapper {
-                name: "LongestRunOfOnes",
-                enable: true,
-                param: None,
-                test_cb: |data, _| match longest_run_of_ones_test(data) {
-                    Ok(v) => Ok(vec![v]),
-                    Err(s) => Err(Some(s)),
-                },
-            },
-            NistWrapper {
-                name: "Rank",
-                enable: true,
-                param: None,
-                test_cb: |data, _| match rank_test(data) {
-                    Ok(v) => Ok(vec![v]),
-                    Err(s) => Err(Some(s)),
-                },
-            },
-            NistWrapper {
-                name: "FFT",
-                enable: true,
-                param: None,
-                test_cb: |data, _| Ok(vec![fft_test(data)]),
-            },
-            NistWrapper {
-                name: "NonOverlappingTemplate",
-                enable: true,
-                param: Some(TestParam {
-                    _min_value: 2,
-                    _max_value: 16,
-                    value: 9,
-                }),
-                test_cb: |data, param| match non_overlapping_template_test(
-                    data,
-                    param.unwrap().value,
-                ) {
-                    Ok(v) => Ok(v),
-                    Err(s) => Err(Some(s)),
-                },
-            },
-            NistWrapper {
-                name: "Overlapping",
-                enable: true,
-                param: Some(TestParam {
-                    _min_value: 2,
-                    _max_value: 1000,
-                    value: 9,
-                }),
-                test_cb: |data, param| {
-                    Ok(vec![overlapping_template_test(data, param.unwrap().value)])
-                },
+    pub params: Vec<TestParam>,
+    pub test: Arc<dyn RandomnessTest>,
+}
+
+impl NistWrapper {
+    /// Enabled test with default parameters.
+    pub fn new(test: Arc<dyn RandomnessTest>) -> Self {
+        Self {
+            name: test.name(),
+            enable: true,
+            params: test.params().iter().copied().map(TestParam::new).collect(),
+            test,
+        }
+    }
+
+    pub fn param(&self, name: &str) -> Option<&TestParam> {
+        self.params
+            .iter()
+            .find(|p| p.spec.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn param_mut(&mut self, name: &str) -> Option<&mut TestParam> {
+        self.params
+            .iter_mut()
+            .find(|p| p.spec.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn run(&self, data: &BitsData) -> Result<Vec<TestResultT>, Option<String>> {
+        self.test.run(data, &self.params)
+    }
+
+    pub fn labels(&self) -> Vec<String> {
+        self.test.labels(&self.params)
+    }
+
+    pub fn min_length(&self) -> usize {
+        self.test.min_length(&self.params)
+    }
+
+    pub fn max_length(&self) -> usize {
+        self.test.max_length(&self.params)
+    }
+}
+
+/// 2^`exp`, saturated on overflow.
+pub(crate) fn pow2(exp: usize) -> usize {
+    u32::try_from(exp)
+        .ok()
+        .and_then(|exp| 1_usize.checked_shl(exp))
+        .unwrap_or(usize::MAX)
+}
+
+fn no_labels(_: &[TestParam]) -> Vec<String> {
+    vec![]
+}
+
+/// Aperiodic templates of length `m` in the order used by the NonOverlappingTemplate test.
+pub fn aperiodic_templates(m: usize) -> Vec<String> {
+    const MAX_NUM_OF_TEMPLATES: usize = 148;
+
+    (0..1_usize << m)
+        .map(|v| format!("{:0width$b}", v, width = m))
+        .filter(|b| (1..m).all(|k| b[k..] != b[..m - k]))
+        .take(MAX_NUM_OF_TEMPLATES)
+        .collect()
+}
+
+fn states_labels(states: impl Iterator<Item = isize>) -> Vec<String> {
+    states.map(|x| format!("x={:+}", x)).collect()
+}
+
+/// The NIST SP 800-22 tests with default parameters.
+pub fn nist_tests() -> Vec<NistWrapper> {
+    [
+        FnTest {
+            name: "Frequency",
+            min_length: |_| 100,
+            params: vec![],
+            run: |data, _| Ok(vec![frequency_test(data)]),
+            labels: no_labels,
+        },
+        FnTest {
+            name: "BlockFrequency",
+            min_length: |_| 100,
+            params: vec![ParamSpec::int("M", "Length of a block", 10, 1_000_000, 128)],
+            run: |data, params| match block_frequency_test(data, params[0].int()) {
+                Ok(v) => Ok(vec![v]),
+                Err(s) => Err(Some(s)),
             },
-            NistWrapper {
-                name: "Universal",
-                enable: true,
-                param: None,
-                test_cb: |data, _| Ok(vec![universal_test(data)]),
+            labels: no_labels,
+        },
+        FnTest {
+            name: "Runs",
+            min_length: |_| 100,
+            params: vec![],
+            run: |data, _| Ok(vec![runs_test(data)]),
+            labels: no_labels,
+        },
+        FnTest {
+            name: "LongestRunOfOnes",
+            min_length: |_| 128,
+            params: vec![],
+            run: |data, _| match longest_run_of_ones_test(data) {
+                Ok(v) => Ok(vec![v]),
+                Err(s) => Err(Some(s)),
             },
-            NistWrapper {
-                name: "LinearComplexity",
-                enable: true,
-                param: Some(TestParam {
-                    _min_value: 500,
-                    _max_value: 1000,
-                    value: 500,
-                }),
-                test_cb: |data, param| Ok(vec![linear_complexity_test(data, param.unwrap().value)]),
+            labels: no_labels,
+        },
+        FnTest {
+            name: "Rank",
+            min_length: |_| 38_912,
+            params: vec![],
+            run: |data, _| match rank_test(data) {
+                Ok(v) => Ok(vec![v]),
+                Err(s) => Err(Some(s)),
             },
-            NistWrapper {
-                name: "Serial",
-                enable: true,
-                param: Some(TestParam {
-                    _min_value: 2,
-                    _max_value: 128,
-                    value: 16,
-                }),
-                test_cb: |data, param| Ok(serial_test(data, param.unwrap().value).to_vec()),
+            labels: no_labels,
+        },
+        FnTest {
+            name: "FFT",
+            min_length: |_| 1_000,
+            params: vec![],
+            run: |data, _| Ok(vec![fft_test(data)]),
+            labels: no_labels,
+        },
+        FnTest {
+            name: "NonOverlappingTemplate",
+            min_length: |params| 8 * (params[0].int() + 1),
+            params: vec![
+                ParamSpec::int("m", "Length of a template", 2, 16, 9),
+                ParamSpec::int(
+                    "Templates",
+                    "Number of templates to report, from the first one",
+                    1,
+                    148,
+                    148,
+                ),
+            ],
+            run: |data, params| match non_overlapping_template_test(data, params[0].int()) {
+                Ok(mut v) => {
+                    v.truncate(params[1].int());
+                    Ok(v)
+                }
+                Err(s) => Err(Some(s)),
             },
-            NistWrapper {
-                name: "ApproximateEntropy",
-                enable: true,
-                param: Some(TestParam {
-                    _min_value: 2,
-                    _max_value: 100,
-                    value: 10,
-                }),
-                test_cb: |data, param| {
-                    Ok(vec![approximate_entropy_test(data, param.unwrap().value)])
-                },
+            labels: |params| {
+                let mut labels = aperiodic_templates(params[0].int());
+                labels.truncate(params[1].int());
+                labels
             },
-            NistWrapper {
-                name: "CumulativeSums",
-                enable: true,
-                param: None,
-                test_cb: |data, _| Ok(cumulative_sums_test(data).to_vec()),
+        },
+        FnTest {
+            name: "Overlapping",
+            min_length: |_| 1_000_000,
+            params: vec![ParamSpec::int("m", "Length of the template", 2, 1000, 9)],
+            run: |data, params| Ok(vec![overlapping_template_test(data, params[0].int())]),
+            labels: no_labels,
+        },
+        FnTest {
+            name: "Universal",
+            min_length: |params| universal::min_length(params[0].int(), params[1].int()),
+            params: vec![
+                ParamSpec::int_or_auto(
+                    "L",
+                    "Length of a block, 0 - chosen by the length of the sequence",
+                    6,
+                    16,
+                ),
+                ParamSpec::int(
+                    "Q",
+                    "Number of initialization blocks, 0 - 10 * 2^L",
+                    0,
+                    10_000_000,
+                    0,
+                ),
+            ],
+            run: |data, params| match universal::universal_test(
+                data,
+                params[0].int(),
+                params[1].int(),
+            ) {
+                Ok(v) => Ok(vec![v]),
+                Err(s) => Err(Some(s)),
             },
-            NistWrapper {
-                name: "RandomExcursions",
-                enable: true,
-                param: None,
-                test_cb: |data, _| match random_excursions_test(data) {
-                    Ok(v) => Ok(v.to_vec()),
-                    Err(_) => Err(None),
-                },
+            labels: no_labels,
+        },
+        FnTest {
+            name: "LinearComplexity",
+            min_length: |_| 1_000_000,
+            params: vec![ParamSpec::int("M", "Length of a block", 500, 5000, 500)],
+            run: |data, params| Ok(vec![linear_complexity_test(data, params[0].int())]),
+            labels: no_labels,
+        },
+        FnTest {
+            name: "Serial",
+            min_length: |params| pow2(params[0].int() + 3),
+            params: vec![ParamSpec::int("m", "Length of a pattern", 2, 128, 16)],
+            run: |data, params| Ok(serial_test(data, params[0].int()).to_vec()),
+            labels: |_| vec!["P1".to_string(), "P2".to_string()],
+        },
+        FnTest {
+            name: "ApproximateEntropy",
+            min_length: |params| pow2(params[0].int() + 6),
+            params: vec![ParamSpec::int("m", "Length of a pattern", 2, 100, 10)],
+            run: |data, params| Ok(vec![approximate_entropy_test(data, params[0].int())]),
+            labels: no_labels,
+        },
+        FnTest {
+            name: "CumulativeSums",
+            min_length: |_| 100,
+            params: vec![],
+            run: |data, _| Ok(cumulative_sums_test(data).to_vec()),
+            labels: |_| vec!["Forward".to_string(), "Backward".to_string()],
+        },
+        FnTest {
+            name: "RandomExcursions",
+            min_length: |_| 1_000_000,
+            params: vec![],
+            run: |data, _| match random_excursions_test(data) {
+                Ok(v) => Ok(v.to_vec()),
+                Err(_) => Err(None),
             },
-            NistWrapper {
-                name: "RandomExcursionsVariant",
-                enable: true,
-                param: None,
-                test_cb: |data, _| match random_excursions_variant_test(data) {
-                    Ok(v) => Ok(v.to_vec()),
-                    Err(_) => Err(None),
-                },
+            labels: |_| states_labels((-4..=4).filter(|x| *x != 0)),
+        },
+        FnTest {
+            name: "RandomExcursionsVariant",
+            min_length: |_| 1_000_000,
+            params: vec![],
+            run: |data, _| match random_excursions_variant_test(data) {
+                Ok(v) => Ok(v.to_vec()),
+                Err(_) => Err(None),
             },
-        ])
-    };
-    pub static ref STOP_FLAG: Mutex<bool> = Mutex::new(false);
-    pub static ref COMPLETE_BLOCKS: Mutex<usize> = Mutex::new(0_usize);
-    pub static ref AVR_TIME_TO_BLOCK: Mutex<u128> = Mutex::new(0_u128);
-    pub static ref TOTAL_TIME: Mutex<Duration> = Mutex::new(Duration::new(0, 0));
-    pub static ref NUMBERS_OF_BLOCKS: Mutex<usize> = Mutex::new(0_usize);
+            labels: |_| states_labels((-9..=9).filter(|x| *x != 0)),
+        },
+    ]
+    .into_iter()
+    .map(|test| NistWrapper::new(Arc::new(test)))
+    .collect()
 }
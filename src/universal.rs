@@ -0,0 +1,85 @@
+use nistrs::{BitsData, TestResultT, TEST_THRESHOLD};
+use statrs::function::erf::erfc;
+
+/// Expected value and variance of the statistic for L = 6..=16 (SP 800-22, 2.9.7).
+const EXPECTED_VALUE: [f64; 17] = [
+    0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 5.2177052, 6.1962507, 7.1836656, 8.1764248, 9.1723243, 10.170032,
+    11.168765, 12.168070, 13.167693, 14.167488, 15.167379,
+];
+
+const VARIANCE: [f64; 17] = [
+    0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 2.954, 3.125, 3.238, 3.311, 3.356, 3.384, 3.401, 3.410, 3.416,
+    3.419, 3.421,
+];
+
+/// Block length recommended for `n` bits, the same as in nistrs.
+pub fn default_l(n: usize) -> usize {
+    match n {
+        1059061760.. => 16,
+        496435200.. => 15,
+        231669760.. => 14,
+        107560960.. => 13,
+        49643520.. => 12,
+        22753280.. => 11,
+        10342400.. => 10,
+        4654080.. => 9,
+        2068480.. => 8,
+        904960.. => 7,
+        _ => 6,
+    }
+}
+
+/// Recommended length of a sequence: Q >= 10 * 2^L initialization blocks
+/// and K ~ 1000 * 2^L test blocks.
+pub fn min_length(l: usize, q: usize) -> usize {
+    if l == 0 {
+        return 387_840;
+    }
+
+    let q = if q == 0 { 10 << l } else { q };
+    (q + (1000 << l)) * l
+}
+
+/// Maurer's "Universal Statistical" test with the block length `l` and
+/// the number of initialization blocks `q`, 0 selects the recommended values.
+pub fn universal_test(data: &BitsData, l: usize, q: usize) -> Result<TestResultT, String> {
+    let n = data.len();
+    let l = if l == 0 { default_l(n) } else { l };
+    if !(6..=16).contains(&l) {
+        return Err(format!("L = {} is out of range 6..16", l));
+    }
+
+    let p = 1_usize << l;
+    let q = if q == 0 { 10 * p } else { q };
+    if n / l <= q {
+        return Err(format!(
+            "{} bits are too few for L = {} and Q = {}",
+            n, l, q
+        ));
+    }
+    let k = n / l - q;
+
+    let block = |i: usize| (0..l).fold(0, |acc, j| (acc << 1) | data[i * l + j] as usize);
+
+    // The last index of each pattern, 1-based.
+    let mut t = vec![0_usize; p];
+    (1..=q).for_each(|i| t[block(i - 1)] = i);
+
+    let sum: f64 = (q + 1..=q + k)
+        .map(|i| {
+            let dec = block(i - 1);
+            let dist = (i - t[dec]) as f64;
+            t[dec] = i;
+            dist.log2()
+        })
+        .sum();
+
+    let c = 0.7 - 0.8 / (l as f64)
+        + (4_f64 + 32_f64 / (l as f64)) * (k as f64).powf(-3_f64 / (l as f64)) / 15_f64;
+    let sigma = c * (VARIANCE[l] / (k as f64)).sqrt();
+
+    let phi = sum / (k as f64);
+    let p_value = erfc((phi - EXPECTED_VALUE[l]).abs() / (2_f64.sqrt() * sigma));
+
+    Ok((p_value >= TEST_THRESHOLD, p_value))
+}
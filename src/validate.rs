@@ -0,0 +1,257 @@
+use std::fmt;
+
+use crate::battery::BatteryKind;
+use crate::tests::{NistWrapper, ParamKind, ParamValue, TestParam};
+use crate::universal;
+
+/// How to resolve an [`Issue`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fix {
+    /// Set the parameter of the test to the value.
+    SetParam(&'static str, usize),
+    /// The test can't run on blocks of this size.
+    Disable,
+}
+
+/// An enabled test whose parameters or block size break recommendations of the test.
+#[derive(Clone, Debug)]
+pub struct Issue {
+    pub test: &'static str,
+    pub message: String,
+    pub fix: Fix,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.fix {
+            Fix::SetParam(name, v) => write!(
+                f,
+                "{}: {} (suggested: {} = {})",
+                self.test, self.message, name, v
+            ),
+            Fix::Disable => write!(f, "{}: {} (suggested: disable)", self.test, self.message),
+        }
+    }
+}
+
+fn min_length(n: usize, min: usize) -> Option<(String, Fix)> {
+    (n < min).then(|| {
+        (
+            format!("needs at least {} bits, block has {}", min, n),
+            Fix::Disable,
+        )
+    })
+}
+
+/// Checks one test against the block size `n` (SP 800-22, section 2 "Input Size Recommendation"),
+/// with `sp800_22` unset only the lengths of the test are checked.
+fn check(test: &NistWrapper, n: usize, sp800_22: bool) -> Option<(String, Fix)> {
+    let value = test.params.first().map_or(0, TestParam::int);
+    let log2_n = (usize::BITS - 1).saturating_sub(n.leading_zeros()) as usize;
+
+    // Parameters which can be adjusted to a shorter sequence.
+    let adjust = match test.name {
+        _ if !sp800_22 => None,
+        "Universal" => {
+            // Q >= 10 * 2^L initialization blocks, L and Q need (Q + 1000 * 2^L) * L bits.
+            let l = if value == 0 {
+                universal::default_l(n)
+            } else {
+                value
+            };
+            let q = test.params.get(1).map_or(0, TestParam::int);
+            if q != 0 && q < 10 << l {
+                Some((
+                    format!("Q = {} must be >= 10 * 2^L = {}", q, 10 << l),
+                    Fix::SetParam("Q", 0),
+                ))
+            } else if value != 0 && n >= 387_840 && n < test.min_length() {
+                Some((
+                    format!("L = {} needs at least {} bits", value, test.min_length()),
+                    Fix::SetParam("L", 0),
+                ))
+            } else if value == 0 && q != 0 && n >= 387_840 && n < universal::min_length(l, q) {
+                Some((
+                    format!(
+                        "Q = {} needs at least {} bits for L = {}",
+                        q,
+                        universal::min_length(l, q),
+                        l
+                    ),
+                    Fix::SetParam("Q", 0),
+                ))
+            } else {
+                None
+            }
+        }
+        "Serial" => (value + 2 >= log2_n).then(|| {
+            (
+                format!(
+                    "m = {} must be < log2(n) - 2 = {}",
+                    value,
+                    log2_n.saturating_sub(2)
+                ),
+                match log2_n.checked_sub(3) {
+                    Some(v) if v >= 2 => Fix::SetParam("m", v),
+                    _ => Fix::Disable,
+                },
+            )
+        }),
+        "ApproximateEntropy" => (value + 5 >= log2_n).then(|| {
+            (
+                format!(
+                    "m = {} must be < log2(n) - 5 = {}",
+                    value,
+                    log2_n.saturating_sub(5)
+                ),
+                match log2_n.checked_sub(6) {
+                    Some(v) if v >= 2 => Fix::SetParam("m", v),
+                    _ => Fix::Disable,
+                },
+            )
+        }),
+        _ => None,
+    };
+
+    adjust
+        .or_else(|| min_length(n, test.min_length()))
+        .or_else(|| {
+            (n > test.max_length()).then(|| {
+                (
+                    format!(
+                        "needs blocks of at most {} bits, block has {}",
+                        test.max_length(),
+                        n
+                    ),
+                    Fix::Disable,
+                )
+            })
+        })
+        .or_else(|| match test.name {
+            _ if !sp800_22 => None,
+            "BlockFrequency" => {
+                // M >= 20, M > 0.01n and N = n / M < 100.
+                let fixed = (n / 100 + 1).max(20);
+                (value < 20 || value * 100 <= n).then(|| {
+                    (
+                        format!("M = {} must be >= 20 and > 0.01n = {}", value, n / 100),
+                        Fix::SetParam("M", fixed),
+                    )
+                })
+            }
+            _ => None,
+        })
+}
+
+/// Checks enabled tests of the battery `kind` against blocks of `n_bits`.
+pub fn validate(kind: BatteryKind, tests: &[NistWrapper], n_bits: usize) -> Vec<Issue> {
+    tests
+        .iter()
+        .filter(|test| test.enable)
+        .filter_map(|test| {
+            let (message, mut fix) = check(test, n_bits, kind == BatteryKind::Nist)?;
+
+            // The suggested value must fit the range of the parameter.
+            if let Fix::SetParam(name, v) = fix {
+                match test.param(name).map(|p| p.spec.kind) {
+                    Some(ParamKind::Int { min, max }) if (min..=max).contains(&v) => {}
+                    Some(ParamKind::IntOrAuto { min, max })
+                        if v == 0 || (min..=max).contains(&v) => {}
+                    _ => fix = Fix::Disable,
+                }
+            }
+
+            Some(Issue {
+                test: test.name,
+                message,
+                fix,
+            })
+        })
+        .collect()
+}
+
+/// Applies suggested fixes; with `disable` set all tests with issues are disabled instead.
+pub fn apply_fixes(tests: &mut [NistWrapper], issues: &[Issue], disable: bool) {
+    for issue in issues {
+        if let Some(test) = tests.iter_mut().find(|test| test.name == issue.test) {
+            match issue.fix {
+                Fix::SetParam(name, v) if !disable => {
+                    if let Some(param) = test.param_mut(name) {
+                        param.value = ParamValue::Int(v);
+                    }
+                }
+                _ => test.enable = false,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{self, ParamSpec};
+
+    /// The SP 800-22 test `name` with integer parameters set to `values`.
+    fn test(name: &str, values: &[(&str, usize)]) -> NistWrapper {
+        let mut test = tests::nist_tests()
+            .into_iter()
+            .find(|test| test.name == name)
+            .unwrap();
+        for (param, value) in values {
+            test.param_mut(param).unwrap().value = ParamValue::Int(*value);
+        }
+        test
+    }
+
+    fn fix(test: NistWrapper, n_bits: usize) -> Option<Fix> {
+        validate(BatteryKind::Nist, &[test], n_bits)
+            .first()
+            .map(|issue| issue.fix)
+    }
+
+    #[test]
+    fn universal() {
+        assert_eq!(fix(test("Universal", &[]), 1_000_000), None);
+        assert_eq!(fix(test("Universal", &[("L", 7)]), 1_000_000), None);
+        assert_eq!(
+            fix(test("Universal", &[("Q", 100)]), 1_000_000),
+            Some(Fix::SetParam("Q", 0))
+        );
+        assert_eq!(
+            fix(test("Universal", &[("L", 10)]), 1_000_000),
+            Some(Fix::SetParam("L", 0))
+        );
+        // L = 7 is chosen for 1000000 bits, Q = 100000 leaves too few test blocks.
+        assert_eq!(
+            fix(test("Universal", &[("Q", 100_000)]), 1_000_000),
+            Some(Fix::SetParam("Q", 0))
+        );
+        assert_eq!(fix(test("Universal", &[]), 100_000), Some(Fix::Disable));
+    }
+
+    #[test]
+    fn linear_complexity() {
+        // M is limited to 500..=5000, so 10^6 bits always give N >= 200 blocks.
+        assert_eq!(
+            fix(test("LinearComplexity", &[("M", 5000)]), 1_000_000),
+            None
+        );
+        assert_eq!(
+            fix(test("LinearComplexity", &[]), 999_999),
+            Some(Fix::Disable)
+        );
+    }
+
+    #[test]
+    fn clamps_params() {
+        let l = ParamSpec::int_or_auto("L", "", 6, 16);
+        assert_eq!(l.default, ParamValue::Int(0));
+        assert_eq!(l.check(ParamValue::Int(0)), Ok(ParamValue::Int(0)));
+        assert_eq!(l.check(ParamValue::Int(3)), Ok(ParamValue::Int(6)));
+        assert_eq!(l.check(ParamValue::Int(20)), Ok(ParamValue::Int(16)));
+        assert!(l.check(ParamValue::Bool(true)).is_err());
+
+        let m = ParamSpec::int("m", "", 2, 10, 2);
+        assert_eq!(m.check(ParamValue::Int(0)), Ok(ParamValue::Int(2)));
+    }
+}